@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::path::{PathBuf, Path};
 
+use crate::feeds::FeedContent;
 use crate::Commands;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -13,22 +14,55 @@ pub struct Config {
     pub description: Option<String>,
     pub ignored_directories: Option<Vec<String>>,
     pub tags_url: Option<String>,
-    pub theme: Option<String>
+    pub theme: Option<String>,
+    /// how many pages section and tag pages list per page, unless a section says otherwise.
+    pub paginate_by: Option<usize>,
+    /// write a robots.txt pointing crawlers to the sitemap.
+    pub robots_txt: Option<bool>,
+    /// write pages as my-note/index.html, and leave ".html" out of every url.
+    pub clean_urls: Option<bool>,
+    /// give pages at the same url as another page one of their own (my-note-2.html),
+    /// instead of skipping them.
+    pub disambiguate_urls: Option<bool>,
+    /// write an index page, from list.html, for folders with pages but no _index.md.
+    pub auto_index: Option<bool>,
+    #[serde(default)]
+    pub feeds: FeedsConfig,
+}
+
+/// the atom.xml and feed.json feeds.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct FeedsConfig {
+    /// "full" to put whole pages in feeds, "summary" for only their summary.
+    pub content: FeedContent,
+    /// the most entries in a feed (the newest ones); all of them if not set.
+    pub limit: Option<usize>,
+    /// also write feeds for each section (a directory with an _index.md).
+    pub sections: bool,
+    /// also write feeds for each tag, next to its tag page.
+    pub tags: bool,
 }
 
 impl Config {
     /// Load the config from a yaml file and get return the Config struct.
-    pub fn new(cwd: &Path, cmd: &Commands) -> Config {
-        let config_file = cwd.join("_esker/config.yaml");
-        let user_config_str = std::fs::read_to_string(&config_file).expect("Failed to load user config.yaml");
-        let mut user_config: Config = serde_yaml::from_str(&user_config_str).expect("Invalid yaml found in config.yaml");
+    pub fn new(cwd: &Path, cmd: &Commands) -> Result<Config, String> {
+        let config_file = Self::path(cwd);
+        let user_config_str = std::fs::read_to_string(&config_file)
+            .map_err(|e| format!("Failed to load user config.yaml: {}", e))?;
+        let mut user_config: Config = serde_yaml::from_str(&user_config_str)
+            .map_err(|e| format!("Invalid yaml found in config.yaml: {}", e))?;
 
         // check if we are in "watch"  mode. If so, set "url" to be localhost:<port>
         if let Commands::Watch { port } = cmd {
             user_config.url = format!("http://localhost:{}", port);
-            return user_config;
+            return Ok(user_config);
         }
 
-        user_config
+        Ok(user_config)
+    }
+
+    pub fn path(cwd: &Path) -> PathBuf {
+        cwd.join("_esker/config.yaml")
     }
 }
@@ -0,0 +1,59 @@
+//! What a page depends on: the pages it links to, the notes it embeds and the names of the
+//! files its links point to. Collected while parsing, so that `esker watch` can rebuild
+//! just the pages a change affects (see `Site::rebuild_changed_markdown`).
+
+use crate::parser::links::VaultIndex;
+use std::collections::BTreeSet;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Dependencies {
+    /// urls (without #fragments) of the pages linked to; they list this page in their backlinks.
+    pub links_to: BTreeSet<String>,
+    /// vault paths of the notes embedded in the page, including the ones embedded in those.
+    pub embeds: BTreeSet<String>,
+    /// the names (lowercased, without extension) of the files links point to, whether they were
+    /// found or not. Adding or removing a file with one of these names can change where they go.
+    pub link_stems: BTreeSet<String>,
+}
+
+impl Dependencies {
+    pub fn clear(&mut self) {
+        self.links_to.clear();
+        self.embeds.clear();
+        self.link_stems.clear();
+    }
+
+    pub fn add_link_target(&mut self, target: &str) {
+        let stem = stem(target);
+        if !stem.is_empty() {
+            self.link_stems.insert(stem);
+        }
+    }
+}
+
+/// the name of the file a link target points to: "../posts/My%20Note.md#intro" -> "my note".
+pub fn stem(target: &str) -> String {
+    let path = target.split('#').next().unwrap_or_default();
+    let path = VaultIndex::normalize(&url_escape::decode(path));
+    path.rsplit('/').next().unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stem() {
+        assert_eq!(stem("../posts/My%20Note.md#intro"), "my note");
+        assert_eq!(stem("First_Post"), "first_post");
+        assert_eq!(stem("#heading"), "");
+    }
+
+    #[test]
+    fn test_add_link_target() {
+        let mut deps = Dependencies::default();
+        deps.add_link_target("posts/first_post#Pellentesque");
+        deps.add_link_target("#same-page");
+        assert_eq!(deps.link_stems.into_iter().collect::<Vec<_>>(), vec!["first_post"]);
+    }
+}
@@ -1,68 +1,523 @@
+use clap::ValueEnum;
 use colored::*;
+use serde::Serialize;
 
 use crate::site::Site;
 
-#[derive(Debug)]
+/// How diagnostics are printed at the end of a build.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum DiagnosticsFormat {
+    /// a summary grouped by kind, listing every diagnostic with -v
+    #[default]
+    Human,
+    /// every diagnostic, as json on stdout
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// What went wrong. Diagnostics are grouped (and reported) in this order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    InvalidConfig,
+    InvalidTemplates,
+    MissingFrontmatter,
+    UnclosedFrontmatter,
+    InvalidFrontmatter,
+    UnknownFrontmatterKey,
+    InvalidDateCreated,
+    InvalidDateUpdated,
+    InvalidPaginateBy,
+    UnresolvedLink,
+    AmbiguousLink,
+    RecursiveEmbed,
+    BrokenLink,
+    MissingAttachment,
+    TemplateRender,
+    WriteFailed,
+    UrlCollision,
+    DisambiguatedUrl,
+    AliasCollision,
+    RemoveFailed,
+}
+
+impl Kind {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Kind::InvalidConfig
+            | Kind::InvalidTemplates
+            | Kind::UnclosedFrontmatter
+            | Kind::InvalidFrontmatter
+            | Kind::BrokenLink
+            | Kind::MissingAttachment
+            | Kind::TemplateRender
+            | Kind::UrlCollision
+            | Kind::AliasCollision => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+
+    /// whether this is found when parsing a file's markdown, rather than loading or rendering it.
+    pub fn is_from_parsing(&self) -> bool {
+        matches!(
+            self,
+            Kind::UnresolvedLink | Kind::AmbiguousLink | Kind::RecursiveEmbed | Kind::MissingAttachment
+        )
+    }
+
+    /// the line printed above a group of diagnostics of this kind.
+    fn summary(&self, count: usize) -> String {
+        match self {
+            Kind::InvalidConfig => format!(
+                "{} problems with your config, the previous one is still in use until it's fixed",
+                count
+            ),
+            Kind::InvalidTemplates => format!(
+                "{} problems loading your templates, the previous ones are still in use until they're fixed",
+                count
+            ),
+            Kind::MissingFrontmatter => format!(
+                "{} files have no frontmatter and were skipped\nstart a file with a {} block to publish it",
+                count,
+                "---".to_string().green().on_black()
+            ),
+            Kind::UnclosedFrontmatter => format!(
+                "{} files have frontmatter that is never closed and were skipped",
+                count
+            ),
+            Kind::InvalidFrontmatter => format!(
+                "{} files have frontmatter that couldn't be read and were skipped",
+                count
+            ),
+            Kind::UnknownFrontmatterKey => format!(
+                "{} frontmatter keys look like misspellings of the ones esker uses\nthey are still available in templates as {}",
+                count,
+                "page.extra".to_string().green().on_black()
+            ),
+            Kind::InvalidDateCreated => format!(
+                "{} files did not have correct {} frontmatter\ndate_created should look like: {}",
+                count,
+                "date_created".to_string().yellow().on_black(),
+                "YYYY-MM-DD HH:MM".to_string().green().on_black()
+            ),
+            Kind::InvalidDateUpdated => format!(
+                "{} files did not have correct {} frontmatter\ndate_updated should look like: {}",
+                count,
+                "date_updated".to_string().yellow().on_black(),
+                "YYYY-MM-DD HH:MM".to_string().green().on_black()
+            ),
+            Kind::InvalidPaginateBy => format!(
+                "{} files did not have a correct {}, it was ignored\npaginate_by should be a number, like: {}",
+                count,
+                "paginate_by".to_string().yellow().on_black(),
+                "20".to_string().green().on_black()
+            ),
+            Kind::UnresolvedLink => format!(
+                "{} wikilinks or embeds do not point to a file in your vault",
+                count
+            ),
+            Kind::AmbiguousLink => format!(
+                "{} links match more than one file in your vault\nuse a longer path (ex: {}) to pick one",
+                count,
+                "[[folder/Note]]".to_string().green().on_black()
+            ),
+            Kind::RecursiveEmbed => format!(
+                "{} embeds end up embedding themselves and were rendered as links instead",
+                count
+            ),
+            Kind::BrokenLink => format!(
+                "{} links point to pages (or headings) that don't exist",
+                count
+            ),
+            Kind::MissingAttachment => format!(
+                "{} images or attachments could not be found in your vault",
+                count
+            ),
+            Kind::TemplateRender => format!(
+                "{} pages could not be rendered with their template and were skipped",
+                count
+            ),
+            Kind::WriteFailed => format!(
+                "{} files could not be written to {}",
+                count,
+                "_esker/_site".to_string().yellow().on_black()
+            ),
+            Kind::UrlCollision => format!(
+                "{} pages have the same url as another page and were skipped\nrename them or change their {} or {} to give each its own",
+                count,
+                "permalink".to_string().green().on_black(),
+                "slug".to_string().green().on_black()
+            ),
+            Kind::DisambiguatedUrl => format!(
+                "{} pages have the same url as another page and were given one of their own\nrename them or set their {} or {} to choose it",
+                count,
+                "permalink".to_string().green().on_black(),
+                "slug".to_string().green().on_black()
+            ),
+            Kind::AliasCollision => format!(
+                "{} aliases have the url of a page and don't redirect\nremove them or move the page with its {} or {}",
+                count,
+                "permalink".to_string().green().on_black(),
+                "slug".to_string().green().on_black()
+            ),
+            Kind::RemoveFailed => format!(
+                "{} old files could not be deleted from {}",
+                count,
+                "_esker/_site".to_string().yellow().on_black()
+            ),
+        }
+    }
+}
+
+/// an error and its causes: tera's errors say which template failed,
+/// the reason is further down the chain.
+pub fn describe(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(e) = source {
+        message = format!("{}: {}", message, e);
+        source = e.source();
+    }
+    message
+}
+
+/// A single problem found while building the site.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub kind: Kind,
+    pub severity: Severity,
+    pub file: String,
+    /// the line in `file`, when it's known.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(kind: Kind, file: String, line: Option<usize>, message: String) -> Diagnostic {
+        Diagnostic { kind, severity: kind.severity(), file, line, message }
+    }
+
+    /// where the diagnostic happened: path/to/file.md:12
+    pub fn location(&self) -> String {
+        match self.line {
+            Some(line) => format!("{}:{}", self.file, line),
+            None => self.file.clone(),
+        }
+    }
+}
+
+/// What gets printed with --diagnostics-format json.
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    errors: usize,
+    warnings: usize,
+    diagnostics: Vec<&'a Diagnostic>,
+}
+
+/// Every diagnostic collected during a build, in the order they were found.
+#[derive(Debug, Default)]
 pub struct Errors {
-    invalid_date_created: Vec<String>,
-    invalid_date_updated: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl Errors {
     pub fn new() -> Errors {
-        Errors {
-            invalid_date_created: Vec::new(),
-            invalid_date_updated: Vec::new(),
-        }
+        Errors { diagnostics: Vec::new() }
+    }
+
+    pub fn add(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// keeps only the diagnostics `keep` returns true for, ex: to drop the ones that are about
+    /// to be found again.
+    pub fn retain(&mut self, keep: impl FnMut(&Diagnostic) -> bool) {
+        self.diagnostics.retain(keep);
     }
 
-    pub fn add_invalid_date_created(&mut self, filepath_str: String) {
-        self.invalid_date_created.push(filepath_str);
+    pub fn add_missing_frontmatter(&mut self, filepath_str: String) {
+        self.add(Diagnostic::new(
+            Kind::MissingFrontmatter,
+            filepath_str,
+            None,
+            "the file has no frontmatter".to_string(),
+        ));
     }
 
-    pub fn add_invalid_date_updated(&mut self, filepath_str: String) {
-        self.invalid_date_updated.push(filepath_str);
+    pub fn add_unclosed_frontmatter(&mut self, filepath_str: String, delimiter: &str) {
+        self.add(Diagnostic::new(
+            Kind::UnclosedFrontmatter,
+            filepath_str,
+            Some(1),
+            format!("the frontmatter is missing its closing {}", delimiter),
+        ));
+    }
+
+    pub fn add_invalid_frontmatter(&mut self, filepath_str: String, error: &str) {
+        self.add(Diagnostic::new(Kind::InvalidFrontmatter, filepath_str, None, error.to_string()));
+    }
+
+    pub fn add_unknown_frontmatter_key(&mut self, filepath_str: String, line: Option<usize>, key: &str, suggestion: &str) {
+        self.add(Diagnostic::new(
+            Kind::UnknownFrontmatterKey,
+            filepath_str,
+            line,
+            format!("unknown key `{}`, did you mean `{}`?", key, suggestion),
+        ));
+    }
+
+    pub fn add_invalid_date_created(&mut self, filepath_str: String, line: Option<usize>, value: &str) {
+        self.add(Diagnostic::new(
+            Kind::InvalidDateCreated,
+            filepath_str,
+            line,
+            format!("`{}` is not a valid date_created", value),
+        ));
+    }
+
+    pub fn add_invalid_date_updated(&mut self, filepath_str: String, line: Option<usize>, value: &str) {
+        self.add(Diagnostic::new(
+            Kind::InvalidDateUpdated,
+            filepath_str,
+            line,
+            format!("`{}` is not a valid date_updated", value),
+        ));
+    }
+
+    pub fn add_invalid_paginate_by(&mut self, filepath_str: String, line: Option<usize>, value: &str) {
+        self.add(Diagnostic::new(
+            Kind::InvalidPaginateBy,
+            filepath_str,
+            line,
+            format!("`{}` is not a valid paginate_by", value),
+        ));
+    }
+
+    pub fn add_unresolved_link(&mut self, filepath_str: String, line: usize, target: &str) {
+        self.add(Diagnostic::new(
+            Kind::UnresolvedLink,
+            filepath_str,
+            Some(line),
+            format!("`{}` does not point to a file in your vault", target),
+        ));
+    }
+
+    pub fn add_ambiguous_link(&mut self, filepath_str: String, line: usize, target: &str, candidates: &[String]) {
+        self.add(Diagnostic::new(
+            Kind::AmbiguousLink,
+            filepath_str,
+            Some(line),
+            format!("`{}` could be any of: {}", target, candidates.join(", ")),
+        ));
+    }
+
+    pub fn add_recursive_embed(&mut self, filepath_str: String, line: usize, chain: &[String]) {
+        self.add(Diagnostic::new(
+            Kind::RecursiveEmbed,
+            filepath_str,
+            Some(line),
+            format!("the embed ends up embedding itself: {}", chain.join(" -> ")),
+        ));
+    }
+
+    pub fn add_broken_link(&mut self, filepath_str: String, line: Option<usize>, target: &str, text: &str) {
+        self.add(Diagnostic::new(
+            Kind::BrokenLink,
+            filepath_str,
+            line,
+            format!("{} (\"{}\") doesn't exist", target, text),
+        ));
+    }
+
+    pub fn add_missing_attachment(&mut self, filepath_str: String, line: usize, target: &str) {
+        self.add(Diagnostic::new(
+            Kind::MissingAttachment,
+            filepath_str,
+            Some(line),
+            format!("`{}` could not be found", target),
+        ));
+    }
+
+    pub fn add_remove_error(&mut self, filepath_str: String, error: &std::io::Error) {
+        self.add(Diagnostic::new(
+            Kind::RemoveFailed,
+            filepath_str,
+            None,
+            format!("couldn't delete the file: {}", error),
+        ));
+    }
+
+    pub fn add_template_error(&mut self, filepath_str: String, error: &tera::Error) {
+        self.add(Diagnostic::new(Kind::TemplateRender, filepath_str, None, describe(error)));
+    }
+
+    pub fn add_invalid_config(&mut self, filepath_str: String, error: &str) {
+        self.add(Diagnostic::new(Kind::InvalidConfig, filepath_str, None, error.to_string()));
+    }
+
+    pub fn add_invalid_templates(&mut self, dir_str: String, error: &str) {
+        self.add(Diagnostic::new(Kind::InvalidTemplates, dir_str, None, error.to_string()));
+    }
+
+    pub fn add_write_error(&mut self, filepath_str: String, error: &std::io::Error) {
+        self.add(Diagnostic::new(
+            Kind::WriteFailed,
+            filepath_str,
+            None,
+            format!("couldn't write the file: {}", error),
+        ));
+    }
+
+    pub fn add_url_collision(&mut self, filepath_str: String, url: &str, other_filepath_str: &str) {
+        self.add(Diagnostic::new(
+            Kind::UrlCollision,
+            filepath_str,
+            None,
+            format!("{} is also the url of {}, so this page was skipped", url, other_filepath_str),
+        ));
+    }
+
+    pub fn add_disambiguated_url(&mut self, filepath_str: String, url: &str, other_filepath_str: &str, new_url: &str) {
+        self.add(Diagnostic::new(
+            Kind::DisambiguatedUrl,
+            filepath_str,
+            None,
+            format!("{} is also the url of {}, so this page is at {}", url, other_filepath_str, new_url),
+        ));
+    }
+
+    pub fn add_alias_collision(&mut self, filepath_str: String, alias: &str, other_filepath_str: &str) {
+        self.add(Diagnostic::new(
+            Kind::AliasCollision,
+            filepath_str,
+            None,
+            format!("the alias {} is the url of {}, so it doesn't redirect", alias, other_filepath_str),
+        ));
+    }
+
+    pub fn report(&self, format: DiagnosticsFormat, verbose: bool) {
+        match format {
+            DiagnosticsFormat::Human => {
+                if self.has_errors() {
+                    self.report_errors(verbose);
+                }
+            }
+            DiagnosticsFormat::Json => self.report_json(),
+        }
     }
 
     pub fn report_errors(&self, verbose: bool) {
-        println!("\n⚠️  Errors and Warnings",);
+        println!(
+            "\n⚠️  Errors and Warnings ({} errors, {} warnings)",
+            self.count(Severity::Error),
+            self.count(Severity::Warning)
+        );
         if !verbose {
             println!("Pass a {} flag to print additional information", "-v".yellow().on_black());
         }
 
-        if !self.invalid_date_created.is_empty() {
-            println!(
-                "\n{} files did not have correct {} frontmatter\ndate_created should look like: {}",
-                self.invalid_date_created.len(),
-                "date_created".to_string().yellow().on_black(),
-                "YYYY-MM-DD HH:MM".to_string().green().on_black()
-            );
+        let sorted = self.sorted();
+        let mut kinds: Vec<Kind> = sorted.iter().map(|d| d.kind).collect();
+        kinds.sort();
+        kinds.dedup();
+
+        for kind in kinds {
+            let diagnostics: Vec<&Diagnostic> = sorted.iter().copied().filter(|d| d.kind == kind).collect();
+            println!("\n{}", kind.summary(diagnostics.len()));
 
             if verbose {
-                println!("\nThe following files have invalid date_created frontmatter\n{:#?}", self.invalid_date_created);
+                println!();
+                for diagnostic in diagnostics {
+                    println!("  {}: {}", diagnostic.location(), diagnostic.message);
+                }
             }
         }
+    }
 
-        if !self.invalid_date_updated.is_empty() {
-            println!(
-                "\n{} files did not have correct {} frontmatter\ndate_updated should look like: {}",
-                self.invalid_date_updated.len(),
-                "date_updated".to_string().yellow().on_black(),
-                "YYYY-MM-DD HH:MM".to_string().green().on_black()
-            );
+    fn report_json(&self) {
+        let report = JsonReport {
+            errors: self.count(Severity::Error),
+            warnings: self.count(Severity::Warning),
+            diagnostics: self.sorted(),
+        };
+        println!("{}", serde_json::to_string_pretty(&report).expect("diagnostics are always serializable"));
+    }
 
-            if verbose {
-                println!("\nThe following files have invalid date_updated frontmatter\n{:#?}", self.invalid_date_updated);
-            }
-        }
+    /// the diagnostics by file and line, rather than the order they were found in.
+    fn sorted(&self) -> Vec<&Diagnostic> {
+        let mut sorted: Vec<&Diagnostic> = self.diagnostics.iter().collect();
+        sorted.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+        sorted
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.diagnostics.iter().filter(|d| d.severity == severity).count()
+    }
+
+    /// moves every error from `other` into these ones.
+    pub fn append(&mut self, other: &mut Errors) {
+        self.diagnostics.append(&mut other.diagnostics);
     }
 
     pub fn has_errors(&self) -> bool {
-        !(self.invalid_date_updated.is_empty() && self.invalid_date_updated.is_empty())
+        !self.diagnostics.is_empty()
     }
 
     pub fn clear(&mut self) {
-        self.invalid_date_created.clear();
-        self.invalid_date_updated.clear();
+        self.diagnostics.clear();
+    }
+
+    pub fn has_broken_links(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.kind == Kind::BrokenLink)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostics() {
+        let mut errors = Errors::new();
+        errors.add_unresolved_link("notes/a.md".to_string(), 3, "Nowhere");
+        errors.add_broken_link("notes/b.md".to_string(), None, "/c.html", "c");
+
+        let mut other = Errors::new();
+        other.add_missing_frontmatter("notes/d.md".to_string());
+        errors.append(&mut other);
+
+        let diagnostics = errors.diagnostics();
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].location(), "notes/a.md:3");
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].location(), "notes/b.md");
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+        assert_eq!(diagnostics[2].kind, Kind::MissingFrontmatter);
+        assert!(errors.has_broken_links());
+        assert_eq!(errors.count(Severity::Error), 1);
+    }
+
+    #[test]
+    fn test_json() {
+        let diagnostic = Diagnostic::new(
+            Kind::UnresolvedLink,
+            "notes/a.md".to_string(),
+            Some(3),
+            "`Nowhere` does not point to a file in your vault".to_string(),
+        );
+        let json = serde_json::to_value(&diagnostic).unwrap();
+        assert_eq!(json["kind"], "unresolved_link");
+        assert_eq!(json["severity"], "warning");
+        assert_eq!(json["line"], 3);
     }
 }
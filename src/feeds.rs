@@ -0,0 +1,200 @@
+//! Atom (https://www.rfc-editor.org/rfc/rfc4287) and JSON Feed 1.1
+//! (https://www.jsonfeed.org/version/1.1/) feeds, written next to the feed.rss template.
+
+use chrono::{Local, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+/// what goes in a feed's entries.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedContent {
+    /// the whole page (and its summary, if it has one).
+    #[default]
+    Full,
+    /// only the page's summary.
+    Summary,
+}
+
+/// a feed: of the whole site, a section or a tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Feed {
+    pub title: String,
+    pub description: Option<String>,
+    /// the site's title, as atom feeds need an author.
+    pub author: String,
+    /// the page the feed is for.
+    pub home_page_url: String,
+    pub atom_url: String,
+    pub json_url: String,
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    pub url: String,
+    pub title: String,
+    pub summary: Option<String>,
+    /// None when only summaries go in the feed.
+    pub content_html: Option<String>,
+    pub date_published: NaiveDateTime,
+    pub date_updated: NaiveDateTime,
+    pub tags: Vec<String>,
+}
+
+pub fn atom(feed: &Feed) -> String {
+    let text = |text: &str| html_escape::encode_text(text).to_string();
+    let attribute = |text: &str| html_escape::encode_double_quoted_attribute(text).to_string();
+    let updated = feed.items.iter().map(|item| item.date_updated).max();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("  <title>{}</title>\n", text(&feed.title)));
+    if let Some(description) = &feed.description {
+        xml.push_str(&format!("  <subtitle>{}</subtitle>\n", text(description)));
+    }
+    xml.push_str(&format!("  <link href=\"{}\" rel=\"alternate\"/>\n", attribute(&feed.home_page_url)));
+    xml.push_str(&format!("  <link href=\"{}\" rel=\"self\"/>\n", attribute(&feed.atom_url)));
+    xml.push_str(&format!("  <id>{}</id>\n", text(&feed.atom_url)));
+    xml.push_str(&format!("  <updated>{}</updated>\n", updated.map(rfc3339).unwrap_or_else(|| rfc3339(NaiveDateTime::default()))));
+    xml.push_str(&format!("  <author><name>{}</name></author>\n", text(&feed.author)));
+    xml.push_str("  <generator>Esker</generator>\n");
+
+    for item in &feed.items {
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!("    <title>{}</title>\n", text(&item.title)));
+        xml.push_str(&format!("    <link href=\"{}\" rel=\"alternate\"/>\n", attribute(&item.url)));
+        xml.push_str(&format!("    <id>{}</id>\n", text(&item.url)));
+        xml.push_str(&format!("    <published>{}</published>\n", rfc3339(item.date_published)));
+        xml.push_str(&format!("    <updated>{}</updated>\n", rfc3339(item.date_updated)));
+        for tag in &item.tags {
+            xml.push_str(&format!("    <category term=\"{}\"/>\n", attribute(tag)));
+        }
+        if let Some(summary) = &item.summary {
+            xml.push_str(&format!("    <summary>{}</summary>\n", text(summary)));
+        }
+        if let Some(content_html) = &item.content_html {
+            xml.push_str(&format!("    <content type=\"html\">{}</content>\n", text(content_html)));
+        }
+        xml.push_str("  </entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+#[derive(Serialize)]
+struct JsonFeed<'a> {
+    version: &'static str,
+    title: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    home_page_url: &'a str,
+    feed_url: &'a str,
+    items: Vec<JsonFeedItem<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonFeedItem<'a> {
+    id: &'a str,
+    url: &'a str,
+    title: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_html: Option<&'a str>,
+    // every item needs content; in summary mode, that's the summary (or nothing).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_text: Option<&'a str>,
+    date_published: String,
+    date_modified: String,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    tags: &'a [String],
+}
+
+pub fn json(feed: &Feed) -> String {
+    let items = feed
+        .items
+        .iter()
+        .map(|item| JsonFeedItem {
+            id: &item.url,
+            url: &item.url,
+            title: &item.title,
+            summary: item.summary.as_deref(),
+            content_html: item.content_html.as_deref(),
+            content_text: match item.content_html {
+                Some(_) => None,
+                None => Some(item.summary.as_deref().unwrap_or_default()),
+            },
+            date_published: rfc3339(item.date_published),
+            date_modified: rfc3339(item.date_updated),
+            tags: &item.tags,
+        })
+        .collect();
+    let json_feed = JsonFeed {
+        version: "https://jsonfeed.org/version/1.1",
+        title: &feed.title,
+        description: feed.description.as_deref(),
+        home_page_url: &feed.home_page_url,
+        feed_url: &feed.json_url,
+        items,
+    };
+    serde_json::to_string_pretty(&json_feed).unwrap_or_default()
+}
+
+// dates in frontmatter (and from file metadata) are local times.
+fn rfc3339(date: NaiveDateTime) -> String {
+    match Local.from_local_datetime(&date).earliest() {
+        Some(date) => date.to_rfc3339(),
+        None => Utc.from_utc_datetime(&date).to_rfc3339(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(content_html: Option<&str>) -> Feed {
+        let date = NaiveDateTime::parse_from_str("2023-01-12 09:30:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        Feed {
+            title: "Notes & Such".to_string(),
+            description: None,
+            author: "Me".to_string(),
+            home_page_url: "https://example.com".to_string(),
+            atom_url: "https://example.com/atom.xml".to_string(),
+            json_url: "https://example.com/feed.json".to_string(),
+            items: vec![Item {
+                url: "https://example.com/a.html?x=1&y=2".to_string(),
+                title: "<A> post".to_string(),
+                summary: Some("short".to_string()),
+                content_html: content_html.map(String::from),
+                date_published: date,
+                date_updated: date,
+                tags: vec!["rust".to_string()],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_atom() {
+        let xml = atom(&feed(Some("<p>hi &amp; bye</p>")));
+        assert!(xml.contains("<title>Notes &amp; Such</title>"));
+        assert!(xml.contains("<author><name>Me</name></author>"));
+        assert!(xml.contains("<link href=\"https://example.com/a.html?x=1&amp;y=2\" rel=\"alternate\"/>"));
+        assert!(xml.contains("<title>&lt;A&gt; post</title>"));
+        assert!(xml.contains("<published>2023-01-12T09:30:00"));
+        assert!(xml.contains("<category term=\"rust\"/>"));
+        assert!(xml.contains("<content type=\"html\">&lt;p&gt;hi &amp;amp; bye&lt;/p&gt;</content>"));
+        assert!(!atom(&feed(None)).contains("<content"));
+    }
+
+    #[test]
+    fn test_json() {
+        let value: serde_json::Value = serde_json::from_str(&json(&feed(Some("<p>hi</p>")))).unwrap();
+        assert_eq!(value["version"], "https://jsonfeed.org/version/1.1");
+        assert_eq!(value["items"][0]["content_html"], "<p>hi</p>");
+        assert_eq!(value["items"][0]["title"], "<A> post");
+        assert!(value["items"][0].get("content_text").is_none());
+
+        let value: serde_json::Value = serde_json::from_str(&json(&feed(None))).unwrap();
+        assert_eq!(value["items"][0]["content_text"], "short");
+        assert!(value.get("description").is_none());
+    }
+}
@@ -1,9 +1,14 @@
 // use crate::util;
-use crate::{site::Site, util};
+use crate::{errors::Errors, util};
+#[cfg(test)]
+use crate::errors::Kind;
 use chrono::prelude::{DateTime, Local, NaiveDateTime};
 use chrono::ParseError;
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
+use std::time::SystemTime;
 
 #[derive(Debug, Clone)]
 pub struct Frontmatter {
@@ -18,26 +23,114 @@ pub struct Frontmatter {
     pub date_updated_timestamp: i64,
     pub template: String,
     pub in_sitemap: bool,
+    /// how many pages a section (_index.md) lists per page.
+    pub paginate_by: Option<usize>,
+    /// the page's url in its folder, instead of the one made from its file name.
+    pub slug: Option<String>,
+    /// the page's url from the top of the site, wherever the file is in the vault.
+    pub permalink: Option<String>,
+    /// the urls (or names) the page used to be at, which redirect to it.
+    pub aliases: Vec<String>,
+    /// any other (custom) fields, available in templates as `page.extra`.
+    pub extra: BTreeMap<String, Value>,
 }
 
-impl Frontmatter {
-    pub fn new(site: &mut Site, md_file_path: &PathBuf) -> Option<Frontmatter> {
-        let metadata = fs::metadata(md_file_path).unwrap();
-        let mut has_valid_fm = true;
+/// the keys esker reads from frontmatter, anything else is a custom field.
+const KNOWN_KEYS: [&str; 14] = [
+    "title",
+    "summary",
+    "tags",
+    "tag",
+    "publish",
+    "template",
+    "date_created",
+    "date_updated",
+    "in_sitemap",
+    "paginate_by",
+    "slug",
+    "permalink",
+    "aliases",
+    "alias",
+];
+
+/// Frontmatter can be written in yaml (between `---` lines) or toml (between `+++` lines).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrontmatterFormat {
+    Yaml,
+    Toml,
+}
+
+impl FrontmatterFormat {
+    /// the format of a file's frontmatter, going by its first line. None if it has none.
+    pub fn detect(contents: &str) -> Option<FrontmatterFormat> {
+        match contents.lines().next()? {
+            "---" => Some(FrontmatterFormat::Yaml),
+            "+++" => Some(FrontmatterFormat::Toml),
+            _ => None,
+        }
+    }
+
+    pub fn delimiter(&self) -> &'static str {
+        match self {
+            FrontmatterFormat::Yaml => "---",
+            FrontmatterFormat::Toml => "+++",
+        }
+    }
+
+    /// parses frontmatter into yaml values; toml is converted so both are handled the same way.
+    fn parse(&self, frontmatter: &str) -> Result<Mapping, String> {
+        match self {
+            FrontmatterFormat::Yaml => {
+                let values: Option<Mapping> = serde_yaml::from_str(frontmatter).map_err(|e| e.to_string())?;
+                Ok(values.unwrap_or_default())
+            }
+            FrontmatterFormat::Toml => {
+                let table: toml::value::Table = toml::from_str(frontmatter).map_err(|e| e.to_string())?;
+                Ok(table.into_iter().map(|(key, value)| (Value::String(key), toml_to_yaml(value))).collect())
+            }
+        }
+    }
+}
 
-        let date_created = metadata.created().expect("failed to get created time.");
-        let date_created: DateTime<Local> = date_created.into();
-        let date_created: NaiveDateTime = date_created.naive_local();
+fn toml_to_yaml(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::Number(i.into()),
+        toml::Value::Float(f) => Value::Number(f.into()),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        // dates are strings in yaml, and parsed as such.
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(values) => Value::Sequence(values.into_iter().map(toml_to_yaml).collect()),
+        toml::Value::Table(table) => Value::Mapping(
+            table.into_iter().map(|(key, value)| (Value::String(key), toml_to_yaml(value))).collect(),
+        ),
+    }
+}
+
+impl Frontmatter {
+    pub fn new(errors: &mut Errors, md_file_path: &PathBuf) -> Option<Frontmatter> {
+        let metadata = match fs::metadata(md_file_path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                let filepath = md_file_path.to_string_lossy().to_string();
+                errors.add_invalid_frontmatter(filepath, &format!("couldn't read the file: {}", e));
+                return None;
+            }
+        };
 
-        let date_updated = metadata.modified().expect("failed to get modified time.");
+        // not every filesystem keeps track of when a file was created.
+        let date_updated = metadata.modified().unwrap_or_else(|_| SystemTime::now());
         let date_updated: DateTime<Local> = date_updated.into();
         let date_updated: NaiveDateTime = date_updated.naive_local();
 
+        let date_created = metadata.created().map(|created| -> DateTime<Local> { created.into() });
+        let date_created: NaiveDateTime = date_created.map_or(date_updated, |created| created.naive_local());
+
         let mut fm = Frontmatter {
-            title: std::ffi::OsString::into_string(
-                md_file_path.file_stem().unwrap().to_os_string(),
-            )
-            .unwrap(),
+            title: md_file_path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
             filepath: md_file_path.clone(),
             date_created,
             date_created_timestamp: date_created.timestamp(),
@@ -48,38 +141,200 @@ impl Frontmatter {
             tags: Vec::new(),
             template: String::from(""),
             in_sitemap: true,
+            paginate_by: None,
+            slug: None,
+            permalink: None,
+            aliases: Vec::new(),
+            extra: BTreeMap::new(),
         };
 
-        let mut capturing = false;
+        let contents = match fs::read_to_string(md_file_path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                errors.add_invalid_frontmatter(fm.get_filepath_as_str(), &format!("couldn't read the file: {}", e));
+                return None;
+            }
+        };
 
-        if let Ok(lines) = util::read_lines(md_file_path) {
-            for line in lines.flatten() {
-                if line != "---" && !capturing {
-                    has_valid_fm = false;
-                    break;
-                }
+        let Some(format) = FrontmatterFormat::detect(&contents) else {
+            errors.add_missing_frontmatter(fm.get_filepath_as_str());
+            return None;
+        };
+
+        let Some(frontmatter) = Self::extract(&contents, format.delimiter()) else {
+            errors.add_unclosed_frontmatter(fm.get_filepath_as_str(), format.delimiter());
+            return None;
+        };
+
+        match format.parse(&frontmatter) {
+            Ok(values) => fm.set_fields(values, &frontmatter, errors),
+            Err(e) => {
+                errors.add_invalid_frontmatter(fm.get_filepath_as_str(), &e);
+                return None;
+            }
+        }
+
+        Some(fm)
+    }
+
+    /// the frontmatter between the `delimiter` lines at the top of a file,
+    /// or None if it's never closed.
+    fn extract(contents: &str, delimiter: &str) -> Option<String> {
+        let mut frontmatter = Vec::new();
+        for line in contents.lines().skip(1) {
+            if line == delimiter {
+                return Some(frontmatter.join("\n"));
+            }
+            frontmatter.push(line);
+        }
+        None
+    }
+
+    /// sets the known fields from the frontmatter; anything else ends up in `extra`.
+    /// `frontmatter` is the text the values were parsed from, to point errors at a line.
+    fn set_fields(&mut self, values: Mapping, frontmatter: &str, errors: &mut Errors) {
+        for (key, value) in values {
+            let Some(key) = key.as_str() else {
+                continue;
+            };
+            let line = Self::key_line(frontmatter, key);
 
-                if line == "---" && !capturing {
-                    capturing = true;
-                    continue;
+            match key {
+                "title" => {
+                    if let Some(title) = Self::as_string(&value) {
+                        self.title = title
+                    }
                 }
+                "date_created" => match Self::as_string(&value).map(|d| Self::match_possible_dates(&d)) {
+                    Some(Ok(res)) => {
+                        self.date_created = res;
+                        self.date_created_timestamp = res.timestamp();
+                    }
+                    _ => {
+                        let value = Self::as_string(&value).unwrap_or_default();
+                        errors.add_invalid_date_created(self.get_filepath_as_str(), line, &value);
+                    }
+                },
 
-                fm.get_key_value_from_line(&line, site);
+                "date_updated" => match Self::as_string(&value).map(|d| Self::match_possible_dates(&d)) {
+                    Some(Ok(res)) => {
+                        self.date_updated = res;
+                        self.date_updated_timestamp = res.timestamp()
+                    }
+                    _ => {
+                        let value = Self::as_string(&value).unwrap_or_default();
+                        errors.add_invalid_date_updated(self.get_filepath_as_str(), line, &value);
+                    }
+                },
+                "summary" => {
+                    self.summary = Self::as_string(&value);
+                }
+                "template" => {
+                    self.template = Self::as_string(&value).unwrap_or_default();
+                }
+                "publish" => {
+                    self.publish = match value {
+                        Value::Bool(publish) => publish,
+                        value => Self::as_string(&value).as_deref() != Some("false"),
+                    }
+                }
+                "paginate_by" => match Self::as_string(&value).and_then(|n| n.parse::<usize>().ok()) {
+                    Some(paginate_by) => self.paginate_by = Some(paginate_by),
+                    None => {
+                        let value = Self::as_string(&value).unwrap_or_default();
+                        errors.add_invalid_paginate_by(self.get_filepath_as_str(), line, &value);
+                    }
+                },
+                "in_sitemap" => {
+                    self.in_sitemap = match value {
+                        Value::Bool(in_sitemap) => in_sitemap,
+                        value => Self::as_string(&value).as_deref() != Some("false"),
+                    }
+                }
+                "slug" => {
+                    self.slug = Self::as_string(&value).filter(|slug| !slug.is_empty());
+                }
+                "permalink" => {
+                    self.permalink = Self::as_string(&value).filter(|permalink| !permalink.is_empty());
+                }
 
-                if line == "---" && capturing {
-                    break;
+                // either a list, or a comma separated string: "tags: books, programming"
+                "tag" | "tags" => self.tags = Self::as_list(value),
+                // like Obsidian's: a list of the note's other names (or old urls).
+                "alias" | "aliases" => self.aliases = Self::as_list(value),
+                // zola and hugo keep custom fields in an `[extra]` table: those are `page.extra.*` as well.
+                "extra" if value.is_mapping() => {
+                    for (key, value) in value.as_mapping().cloned().unwrap_or_default() {
+                        if let Some(key) = key.as_str() {
+                            self.extra.insert(key.to_string(), value);
+                        }
+                    }
+                }
+                _ => {
+                    if let Some(known_key) = Self::misspelled_key(key) {
+                        errors.add_unknown_frontmatter_key(self.get_filepath_as_str(), line, key, known_key);
+                    }
+                    self.extra.insert(key.to_string(), value);
                 }
             }
         }
+    }
+
+    /// the line of the file a top level key is on (the opening delimiter being line 1).
+    fn key_line(frontmatter: &str, key: &str) -> Option<usize> {
+        let position = frontmatter.lines().position(|line| {
+            line.strip_prefix(key)
+                .map(|rest| rest.trim_start())
+                .is_some_and(|rest| rest.starts_with(':') || rest.starts_with('='))
+        })?;
+        Some(position + 2)
+    }
 
-        if has_valid_fm {
-            Some(fm)
-        } else {
-            None
+    /// the key esker knows that an unknown `key` is probably a typo of: "titel" -> "title".
+    /// Custom keys are fine (they end up in `extra`), so only near misses are reported.
+    fn misspelled_key(key: &str) -> Option<&'static str> {
+        let key = key.to_lowercase();
+        KNOWN_KEYS.into_iter().find(|known| {
+            let max_distance = if known.len() > 4 { 2 } else { 1 };
+            util::edit_distance(&key, known) <= max_distance
+        })
+    }
+
+    // a list, or a comma separated string, of strings: "tags: books, programming"
+    fn as_list(value: Value) -> Vec<String> {
+        let values: Vec<String> = match value {
+            Value::Sequence(values) => values.iter().filter_map(Self::as_string).collect(),
+            value => Self::as_string(&value)
+                .map(|values| values.split(',').map(|value| value.to_string()).collect())
+                .unwrap_or_default(),
+        };
+        values
+            .into_iter()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .collect()
+    }
+
+    // scalar values as a string; `title: 2023` is a number as far as yaml is concerned.
+    fn as_string(value: &Value) -> Option<String> {
+        match value {
+            Value::String(s) => Some(s.trim().to_string()),
+            Value::Number(n) => Some(n.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+            _ => None,
         }
     }
 
     fn match_possible_dates(date_str: &str) -> Result<NaiveDateTime, ParseError> {
+        // toml (or iso 8601) datetimes: 2023-01-12T09:30:00, with an optional offset.
+        if let Ok(dc) = DateTime::parse_from_rfc3339(date_str) {
+            return Ok(dc.naive_local());
+        }
+
+        if let Ok(dc) = NaiveDateTime::parse_from_str(date_str, "%Y-%m-%dT%H:%M:%S%.f") {
+            return Ok(dc);
+        }
+
         if let Ok(dc) = NaiveDateTime::parse_from_str(date_str, "%Y-%m-%d %H:%M:%S") {
             return Ok(dc);
         }
@@ -102,68 +357,117 @@ impl Frontmatter {
         }
     }
 
-    pub fn get_key_value_from_line(&mut self, line: &str, site: &mut Site) {
-        if let Some((key, val)) = line.split_once(':') {
-            let lhs = key.trim();
-            let rhs = val.trim();
+    pub fn date_modified_str(&self) -> String {
+        self.date_updated.format("%Y-%m-%d %H:%M").to_string()
+    }
 
-            match lhs {
-                "title" => self.title = rhs.trim().to_string(),
-                "date_created" => match Self::match_possible_dates(rhs) {
-                    Ok(res) => {
-                        self.date_created = res;
-                        self.date_created_timestamp = res.timestamp();
-                    }
-                    Err(_) => {
-                        site.errors
-                            .add_invalid_date_created(self.get_filepath_as_str());
-                    }
-                },
+    pub fn get_filepath_as_str(&self) -> String {
+        self.filepath.to_string_lossy().to_string()
+    }
+}
 
-                "date_updated" => match Self::match_possible_dates(rhs) {
-                    Ok(res) => {
-                        self.date_updated = res;
-                        self.date_updated_timestamp = res.timestamp()
-                    }
-                    Err(_) => {
-                        site.errors
-                            .add_invalid_date_updated(self.get_filepath_as_str());
-                    }
-                },
-                "summary" => {
-                    self.summary = Some(rhs.to_string());
-                }
-                "template" => {
-                    self.template = rhs.to_string();
-                }
-                "publish" => {
-                    self.publish = rhs != "false"
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
 
-                "tag" | "tags" => {
-                    let tags = rhs.split(',');
-                    let vec: Vec<_> = tags
-                        .collect::<Vec<&str>>()
-                        .iter()
-                        .map(|tag| tag.trim().to_string())
-                        .filter(|tag| *tag != "")
-                        .collect();
-                    self.tags = vec;
-                }
-                _ => (),
-            }
-        }
+    fn example_frontmatter() -> Frontmatter {
+        let path = env::current_dir().unwrap().join("tests/example_site/foo.md");
+        Frontmatter::new(&mut Errors::new(), &path).unwrap()
     }
 
-    pub fn date_modified_str(&self) -> String {
-        return self.date_updated.format("%Y-%m-%d %H:%M").to_string();
+    fn set_fields(fm: &mut Frontmatter, format: FrontmatterFormat, frontmatter: &str, errors: &mut Errors) {
+        fm.set_fields(format.parse(frontmatter).unwrap(), frontmatter, errors);
     }
 
-    pub fn get_filepath_as_str(&self) -> String {
-        self.filepath
-            .clone()
-            .into_os_string()
-            .into_string()
-            .unwrap()
+    #[test]
+    fn test_extract() {
+        assert_eq!(FrontmatterFormat::detect("# no frontmatter"), None);
+        assert_eq!(FrontmatterFormat::detect("+++\ntitle = 'a'\n+++"), Some(FrontmatterFormat::Toml));
+        assert_eq!(Frontmatter::extract("---\ntitle: a\n---\nbody", "---"), Some("title: a".to_string()));
+        assert_eq!(Frontmatter::extract("---\r\ntitle: a\r\n---\r\n", "---"), Some("title: a".to_string()));
+        assert_eq!(Frontmatter::extract("---\n---\n", "---"), Some(String::new()));
+        assert_eq!(Frontmatter::extract("---\ntitle: a\n", "---"), None);
+    }
+
+    #[test]
+    fn test_yaml() {
+        let mut fm = example_frontmatter();
+        let yaml = "title: \"Rust: the book\"\ntags:\n  - books\n  - rust\npublish: false\nsummary: >\n  a multi\n  line summary\nauthor: me\nseries:\n  name: reading\n  part: 2";
+        set_fields(&mut fm, FrontmatterFormat::Yaml, yaml, &mut Errors::new());
+
+        assert_eq!(fm.title, "Rust: the book");
+        assert_eq!(fm.tags, vec!["books", "rust"]);
+        assert!(!fm.publish);
+        assert_eq!(fm.summary.as_deref(), Some("a multi line summary"));
+        assert_eq!(fm.extra["author"], Value::String("me".to_string()));
+        assert_eq!(fm.extra["series"]["part"], Value::Number(2.into()));
+    }
+
+    #[test]
+    fn test_yaml_comma_separated_tags_and_dates() {
+        let mut fm = example_frontmatter();
+        let mut errors = Errors::new();
+        let yaml = "tags: elixir, bar\ndate_created: 2023-01-12 09:30\ndate_updated: yesterday";
+        set_fields(&mut fm, FrontmatterFormat::Yaml, yaml, &mut errors);
+
+        assert_eq!(fm.tags, vec!["elixir", "bar"]);
+        assert_eq!(util::naive_date_to_str(fm.date_created), "2023-01-12 09:30");
+        assert!(errors.has_errors());
+        assert!(FrontmatterFormat::Yaml.parse("title: [unclosed").is_err());
+    }
+
+    #[test]
+    fn test_urls() {
+        let mut fm = example_frontmatter();
+        let mut errors = Errors::new();
+        let yaml = "slug: my-post\npermalink: /essays/why-rust/\naliases:\n  - Old Name\n  - /2019/old-url.html";
+        set_fields(&mut fm, FrontmatterFormat::Yaml, yaml, &mut errors);
+
+        assert_eq!(fm.slug.as_deref(), Some("my-post"));
+        assert_eq!(fm.permalink.as_deref(), Some("/essays/why-rust/"));
+        assert_eq!(fm.aliases, vec!["Old Name", "/2019/old-url.html"]);
+        set_fields(&mut fm, FrontmatterFormat::Yaml, "alias: Another, Yet another\nslug: ''", &mut errors);
+        assert_eq!(fm.aliases, vec!["Another", "Yet another"]);
+        assert_eq!(fm.slug, None);
+        assert!(!errors.has_errors());
+    }
+
+    #[test]
+    fn test_toml() {
+        let mut fm = example_frontmatter();
+        let mut errors = Errors::new();
+        let toml = "title = \"Migrated\"\ntags = [\"hugo\", \"zola\"]\ndate_created = 2023-01-12T09:30:00\ndate_updated = \"2023-01-13\"\npublish = false\ntemplate = \"list\"\nsummary = \"from toml\"\n\n[extra]\nauthor = \"me\"";
+        set_fields(&mut fm, FrontmatterFormat::Toml, toml, &mut errors);
+
+        assert_eq!(fm.title, "Migrated");
+        assert_eq!(fm.tags, vec!["hugo", "zola"]);
+        assert_eq!(util::naive_date_to_str(fm.date_created), "2023-01-12 09:30");
+        assert_eq!(util::naive_date_to_str(fm.date_updated), "2023-01-13 17:00");
+        assert!(!fm.publish);
+        assert_eq!(fm.template, "list");
+        assert_eq!(fm.summary.as_deref(), Some("from toml"));
+        assert_eq!(fm.extra["author"], Value::String("me".to_string()));
+        assert!(!errors.has_errors());
+        assert!(FrontmatterFormat::Toml.parse("title = unquoted").is_err());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let mut fm = example_frontmatter();
+        let mut errors = Errors::new();
+        let yaml = "titel: typo\nauthor: me\nTags: rust\ndate_updated: yesterday";
+        set_fields(&mut fm, FrontmatterFormat::Yaml, yaml, &mut errors);
+
+        let diagnostics: Vec<_> = errors.diagnostics().iter().map(|d| (d.kind, d.line)).collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (Kind::UnknownFrontmatterKey, Some(2)),
+                (Kind::UnknownFrontmatterKey, Some(4)),
+                (Kind::InvalidDateUpdated, Some(5)),
+            ]
+        );
+        assert_eq!(fm.extra["titel"], Value::String("typo".to_string()));
     }
 }
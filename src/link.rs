@@ -1,13 +1,15 @@
-/// Link represents any link found in markdown and converted for html.
-/// This module is a mix of a) handling functionality for mapping markdown parsed links -> html
-/// as well as b) functionality for creating links of a certain type, likely to be used in Tera. (sitemap, backlinks).
+//! Link represents any link found in markdown and converted for html.
+//! This module is a mix of a) handling functionality for mapping markdown parsed links -> html
+//! as well as b) functionality for creating links of a certain type, likely to be used in Tera. (sitemap, backlinks).
 
-use crate::{site::Site, md_file::MdFile};
+use crate::{parser::{embeds, links::Resolution}, site::Site, md_file::MdFile};
+use chrono::NaiveDateTime;
 use pulldown_cmark::{CowStr, LinkType, Tag};
 use slugify::slugify;
-use std::path::PathBuf;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
 
-use serde::Serialize;
+use serde::{Serialize, Serializer};
 
 #[derive(Debug)]
 pub struct SiteLinks {
@@ -15,6 +17,12 @@ pub struct SiteLinks {
     pub internal: Vec<Link>,
 }
 
+impl Default for SiteLinks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SiteLinks {
     pub fn new() -> SiteLinks {
         SiteLinks {
@@ -22,6 +30,21 @@ impl SiteLinks {
             internal: Vec::new(),
         }
     }
+
+    pub fn clear(&mut self) {
+        self.external.clear();
+        self.internal.clear();
+    }
+
+    /// removes the links found in a page, returning the internal ones.
+    pub fn remove_from_page(&mut self, page_url: &str) -> Vec<Link> {
+        let from_page = |link: &Link| link.originating_file_url.as_deref() == Some(page_url);
+        self.external.retain(|link| !from_page(link));
+
+        let (removed, kept) = self.internal.drain(..).partition(from_page);
+        self.internal = kept;
+        removed
+    }
 }
 
 
@@ -32,11 +55,19 @@ pub enum EskerLinkType {
     Backlink,
     RelatedLink,
     Tag,
-    TaggedItem {date_created: String},
+    TaggedItem {
+        #[serde(serialize_with = "serialize_date")]
+        date_created: NaiveDateTime,
+    },
     Sitemap {date_created_timestamp: i64},
     Image
 }
 
+/// dates are shown to templates the way they always were: "2022-10-12 05:30:00".
+fn serialize_date<S: Serializer>(date: &NaiveDateTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(date)
+}
+
 #[derive(Clone, Serialize, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub struct Link {
     pub url: String,
@@ -45,7 +76,10 @@ pub struct Link {
     pub originating_file_title: Option<String>,
     pub originating_file_url: Option<String>,
     pub link_type: EskerLinkType,
-    pub original: Option<String>
+    pub original: Option<String>,
+    /// the line the link is on in its file, for error reporting.
+    #[serde(skip)]
+    pub line: Option<usize>,
 }
 
 
@@ -64,7 +98,8 @@ impl Link {
             originating_file_title: None,
             originating_file_url: None,
             original: None,
-            link_type: EskerLinkType::TaggedItem { date_created: md_file.frontmatter.date_created.to_string() }
+            line: None,
+            link_type: EskerLinkType::TaggedItem { date_created: md_file.frontmatter.date_created }
         }
     }
 
@@ -76,29 +111,66 @@ impl Link {
             originating_file_title: None,
             originating_file_url: None,
             original: None,
+            line: None,
             link_type: EskerLinkType::Sitemap { date_created_timestamp: md_file.frontmatter.date_created_timestamp }
         }
     }
 
+    /// an attachment used by a page; `original` is the (url encoded) path of the attachment in the vault.
+    pub fn new_attachment_link(url: String, title: String, original: String) -> Self {
+        Self {
+            url,
+            is_internal: true,
+            title,
+            originating_file_title: None,
+            originating_file_url: None,
+            original: Some(original),
+            line: None,
+            link_type: EskerLinkType::Image,
+        }
+    }
+
+    /// fills the link from a markdown link found by the parser; internal links are looked up
+    /// in the vault relative to `from_dir` (the directory of the file the link is in).
+    /// Links to attachments that exist are added to `attachments`.
+    /// Returns the candidate files if an internal link matches more than one file.
     pub fn fill_from_parser(
         &mut self,
         tag: Tag,
         site: &Site,
+        from_dir: &Path,
         originating_url: Option<String>,
         originating_title: Option<String>,
-    ) {
+        attachments: &mut Vec<Link>,
+    ) -> Option<Vec<String>> {
+        let mut ambiguous_candidates = None;
+
         match tag {
             Tag::Link(_link_type, url, title) => {
-                let mut url_str = Self::slugify_internal_url(url.to_string());
                 if Self::is_internal(&url) {
-                    let url_as_path = PathBuf::from(&url_str).with_extension("html");
-                    url_str = format!("{}", url_as_path.display());
-
                     let mut new_link_url: CowStr;
                     if Self::is_mailto(&url) {
                         new_link_url = url.clone()
+                    } else if url.starts_with('#') {
+                        // a heading on the same page.
+                        let page_url = originating_url.clone().unwrap_or_default();
+                        new_link_url = format!("{}{}", page_url, url).into();
+                    } else if !Self::is_note(&url) {
+                        new_link_url = match Self::find_attachment(site, &url, from_dir) {
+                            Some((full_url, vault_path)) => {
+                                attachments.push(Self::new_attachment_link(full_url.clone(), title.to_string(), vault_path));
+                                full_url.into()
+                            }
+                            None => site.build_with_baseurl(url.trim_start_matches('/').to_string()).into(),
+                        };
                     } else {
-                        new_link_url = site.build_with_baseurl(url_str).into();
+                        new_link_url = Self::build_internal_url(site, &url).into();
+
+                        match Self::resolve_markdown_url(site, &url, from_dir) {
+                            Resolution::Found(full_url) => new_link_url = full_url.into(),
+                            Resolution::Ambiguous(candidates) => ambiguous_candidates = Some(candidates),
+                            Resolution::NotFound => (),
+                        }
                     }
 
                     self.url = new_link_url.to_string();
@@ -115,6 +187,50 @@ impl Link {
             }
             _ => panic!(),
         }
+
+        ambiguous_candidates
+    }
+
+    // markdown links to notes ("../my%20note.md#some-heading") can be relative or the shortest path
+    // to the note, so they are looked up in the vault. Other files (attachments etc) are left alone.
+    fn resolve_markdown_url(site: &Site, url: &str, from_dir: &Path) -> Resolution<String> {
+        let (path, fragment) = match url.split_once('#') {
+            Some((path, fragment)) => (path, Some(fragment)),
+            None => (url, None),
+        };
+        let path = url_escape::decode(path).to_string();
+        let extension = Path::new(&path).extension();
+
+        if path.is_empty() || !(extension.is_none() || extension == Some(OsStr::new("md"))) {
+            return Resolution::NotFound;
+        }
+
+        match site.vault_index.resolve(&path, from_dir) {
+            Resolution::Found(file) => match fragment {
+                Some(fragment) => Resolution::Found(format!("{}#{}", file.full_url, fragment)),
+                None => Resolution::Found(file.full_url.clone()),
+            },
+            Resolution::Ambiguous(candidates) => {
+                Resolution::Ambiguous(candidates.iter().map(|c| c.vault_path.clone()).collect())
+            }
+            Resolution::NotFound => Resolution::NotFound,
+        }
+    }
+
+    // links to anything other than a note ("attachments/my%20doc.pdf") point to the file itself,
+    // found the same way as an embedded attachment. Returns the link's url and the
+    // (url encoded) path of the attachment in the vault.
+    fn find_attachment(site: &Site, url: &str, from_dir: &Path) -> Option<(String, String)> {
+        let (path, rest) = url.split_at(url.find(['?', '#']).unwrap_or(url.len()));
+        let vault_path = embeds::find_attachment(site, &url_escape::decode(path), from_dir)?;
+        let encoded_path = url_escape::encode_fragment(&vault_path).to_string();
+        Some((site.build_with_baseurl(format!("{}{}", encoded_path, rest)), encoded_path))
+    }
+
+    /// whether a link found in markdown points to a note, rather than an attachment.
+    fn is_note(url: &str) -> bool {
+        let path = url.split(['?', '#']).next().unwrap_or_default();
+        embeds::is_note(&url_escape::decode(path))
     }
 
     pub fn empty() -> Link {
@@ -125,17 +241,43 @@ impl Link {
             original: None,
             originating_file_url: None,
             originating_file_title: None,
+            line: None,
             link_type: EskerLinkType::Default
         }
     }
 
     // takes a text link and updates it to add the base url if it's internal.
-    pub fn for_parser<'a>(&self, site: &mut Site) -> Tag<'a> {
+    pub fn for_parser<'a>(&self, site: &Site) -> Tag<'a> {
         let new_link_url: CowStr = self.url.clone().into();
         let title: CowStr = site.build_with_baseurl(self.url.clone()).into();
         Tag::Link(LinkType::Inline, new_link_url, title)
     }
 
+    /// turns an internal link found in markdown ("posts/my post.md") into the full url of its page.
+    pub fn build_internal_url(site: &Site, url: &str) -> String {
+        let url_str = Self::slugify_internal_url(url.to_string());
+        match site.clean_urls() {
+            true => site.build_with_baseurl(format!("{}/", url_str)),
+            false => {
+                let url_as_path = PathBuf::from(&url_str).with_extension("html");
+                site.build_with_baseurl(format!("{}", url_as_path.display()))
+            }
+        }
+    }
+
+    /// the #fragment of the url, if it has one (without the #).
+    pub fn fragment(&self) -> Option<&str> {
+        self.url.split_once('#').map(|(_url, fragment)| fragment)
+    }
+
+    /// the url without any #fragment, ie: the url of the page being linked to.
+    pub fn url_without_fragment(&self) -> &str {
+        match self.url.split_once('#') {
+            Some((url, _fragment)) => url,
+            None => &self.url,
+        }
+    }
+
     // split a url: "projects/my_folder/a file"
     // get the last and slug it and rebuild the url.
     fn slugify_internal_url(url: String) -> String {
@@ -155,12 +297,13 @@ impl Link {
     }
 
     /// responsible for preparing image links found in markdown docs to work on the web
-    /// prepends an attachments with the baseurl.
+    /// prepends an attachments with the baseurl, and adds it to `attachments`.
     pub fn update_img_link<'a>(
         link_type: LinkType,
         url: CowStr<'a>,
         title: CowStr<'a>,
-        site: &mut Site,
+        site: &Site,
+        attachments: &mut Vec<Link>,
     ) -> Tag<'a> {
         let mut url_str = url.to_string();
         if Self::is_internal(&url_str) {
@@ -170,15 +313,8 @@ impl Link {
             let new_link_url: CowStr = full_url.clone().into();
             // > push a link type to site.
 
-            // TODO: turn this into a method!
-            let mut attachment_link = Link::empty();
-            attachment_link.is_internal = true;
-            attachment_link.url = full_url;
-            attachment_link.title = title.to_string();
-            attachment_link.link_type = EskerLinkType::Image;
-            attachment_link.original = Some(url.to_string());
-
-            site.add_attachment(attachment_link);
+            let attachment_link = Link::new_attachment_link(full_url, title.to_string(), url.to_string());
+            attachments.push(attachment_link);
             Tag::Image(link_type, new_link_url, title)
         } else {
             Tag::Image(link_type, url, title)
@@ -0,0 +1,305 @@
+//! Checks that the external links found in a site still work (`esker check-links`).
+//! Results are cached in `_esker/.cache` so that running it again only checks links
+//! that haven't been checked recently.
+
+use crate::{link::Link, site::Site};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const CACHE_FILE: &str = "external_links.yaml";
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LinkStatus {
+    /// the server responded successfully (after following redirects).
+    Ok(u16),
+    /// the server responded with an error status (4xx, 5xx).
+    Broken(u16),
+    /// no response at all: dns failure, refused connection, timeout...
+    Unreachable(String),
+}
+
+impl LinkStatus {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, LinkStatus::Ok(_))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedStatus {
+    status: LinkStatus,
+    /// unix timestamp (seconds) of when the link was checked.
+    checked_at: u64,
+}
+
+/// The results of previous checks, stored in `_esker/.cache/external_links.yaml`.
+#[derive(Debug)]
+pub struct Cache {
+    path: PathBuf,
+    ttl: Duration,
+    entries: HashMap<String, CachedStatus>,
+}
+
+impl Cache {
+    /// loads the cache from `dir`; a missing or unreadable cache file is treated as empty.
+    pub fn load(dir: &Path, ttl: Duration) -> Self {
+        let path = dir.join(CACHE_FILE);
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_yaml::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        Cache { path, ttl, entries }
+    }
+
+    /// the cached status of a url, if it was checked less than `ttl` ago.
+    pub fn get(&self, url: &str) -> Option<&LinkStatus> {
+        let entry = self.entries.get(url)?;
+        let age = now().saturating_sub(entry.checked_at);
+        (age < self.ttl.as_secs()).then_some(&entry.status)
+    }
+
+    pub fn insert(&mut self, url: String, status: LinkStatus) {
+        let checked_at = now();
+        self.entries.insert(url, CachedStatus { status, checked_at });
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = serde_yaml::to_string(&self.entries).map_err(io::Error::other)?;
+        fs::write(&self.path, contents)
+    }
+}
+
+pub struct LinkChecker {
+    agent: ureq::Agent,
+    concurrency: usize,
+}
+
+impl LinkChecker {
+    pub fn new(timeout: Duration, concurrency: usize) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(timeout)
+            .user_agent(concat!("esker/", env!("CARGO_PKG_VERSION")))
+            .build();
+
+        LinkChecker {
+            agent,
+            concurrency: concurrency.max(1),
+        }
+    }
+
+    /// checks a single url, with a HEAD request first as it's cheaper.
+    pub fn check(&self, url: &str) -> LinkStatus {
+        let url = if url.starts_with("www.") {
+            format!("https://{}", url)
+        } else {
+            url.to_string()
+        };
+
+        match self.agent.head(&url).call() {
+            Ok(res) => LinkStatus::Ok(res.status()),
+            // plenty of servers don't answer HEAD requests properly (with an error, or by
+            // dropping the connection), so try again with a GET.
+            Err(_) => match self.agent.get(&url).call() {
+                Ok(res) => LinkStatus::Ok(res.status()),
+                Err(ureq::Error::Status(code, _)) => LinkStatus::Broken(code),
+                Err(e) => LinkStatus::Unreachable(e.to_string()),
+            },
+        }
+    }
+
+    /// checks every url, `concurrency` at a time.
+    pub fn check_all(&self, urls: &[&str]) -> HashMap<String, LinkStatus> {
+        let queue = Mutex::new(urls.iter());
+        let results = Mutex::new(HashMap::new());
+
+        thread::scope(|s| {
+            for _ in 0..self.concurrency.min(urls.len()) {
+                s.spawn(|| loop {
+                    let next = queue.lock().unwrap().next();
+                    let Some(url) = next else { break };
+                    let status = self.check(url);
+                    results.lock().unwrap().insert(url.to_string(), status);
+                });
+            }
+        });
+
+        results.into_inner().unwrap()
+    }
+}
+
+/// Checks the external links of a (built) site, printing a report of the broken ones.
+/// Returns the number of broken links.
+pub fn run(site: &Site, checker: &LinkChecker, ttl: Duration) -> usize {
+    let mut cache = Cache::load(&site.dir_esker.join(".cache"), ttl);
+
+    let mut unchecked: Vec<&str> = site
+        .links
+        .external
+        .iter()
+        .map(|link| link.url.as_str())
+        .filter(|url| cache.get(url).is_none())
+        .collect();
+    unchecked.sort_unstable();
+    unchecked.dedup();
+
+    for (url, status) in checker.check_all(&unchecked) {
+        cache.insert(url, status);
+    }
+
+    if let Err(e) = cache.save() {
+        println!("Failed to write the link cache: {}", e);
+    }
+
+    report(&site.links.external, &cache)
+}
+
+/// prints the broken links, grouped by the page they are on.
+/// Returns the number of broken urls (a url linked to from several pages counts once).
+fn report(links: &[Link], cache: &Cache) -> usize {
+    let mut broken: BTreeMap<&str, Vec<(&str, &LinkStatus)>> = BTreeMap::new();
+    for link in links {
+        match cache.get(&link.url) {
+            Some(status) if !status.is_ok() => {
+                let page = link.originating_file_url.as_deref().unwrap_or("unknown page");
+                broken.entry(page).or_default().push((&link.url, status));
+            }
+            _ => (),
+        }
+    }
+
+    let mut broken_urls: BTreeSet<&str> = BTreeSet::new();
+    for (page, links) in &broken {
+        println!("\n{}", page.bold());
+        for (url, status) in links {
+            let reason = match status {
+                LinkStatus::Broken(code) => code.to_string(),
+                LinkStatus::Unreachable(e) => e.clone(),
+                LinkStatus::Ok(_) => continue,
+            };
+            println!("  {} {} ({})", "-".red(), url, reason);
+            broken_urls.insert(url);
+        }
+    }
+
+    let checked: BTreeSet<&str> = links.iter().map(|link| link.url.as_str()).collect();
+    println!("\nChecked {} external links, {} broken.", checked.len(), broken_urls.len());
+    broken_urls.len()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// a stand-in http server: `/ok` is fine, `/no-head` only answers GETs (`/reset-head` drops
+    /// the connection on a HEAD), anything else is a 404.
+    fn serve() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                // skip the headers.
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+
+                let mut parts = request_line.split_whitespace();
+                let status = match (parts.next(), parts.next()) {
+                    (Some("HEAD"), Some("/reset-head")) => continue,
+                    (_, Some("/ok")) | (Some("GET"), Some("/no-head" | "/reset-head")) => "200 OK",
+                    (Some("HEAD"), Some("/no-head")) => "405 Method Not Allowed",
+                    _ => "404 Not Found",
+                };
+                let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn test_check_all() {
+        let base = serve();
+        let ok = format!("{}/ok", base);
+        let no_head = format!("{}/no-head", base);
+        let reset_head = format!("{}/reset-head", base);
+        let missing = format!("{}/missing", base);
+
+        let checker = LinkChecker::new(Duration::from_secs(5), 2);
+        let results = checker.check_all(&[&ok, &no_head, &reset_head, &missing]);
+
+        assert_eq!(results[&ok], LinkStatus::Ok(200));
+        assert_eq!(results[&no_head], LinkStatus::Ok(200));
+        assert_eq!(results[&reset_head], LinkStatus::Ok(200));
+        assert_eq!(results[&missing], LinkStatus::Broken(404));
+
+        let unreachable = checker.check("http://127.0.0.1:1/nothing-here");
+        assert!(matches!(unreachable, LinkStatus::Unreachable(_)));
+    }
+
+    #[test]
+    fn test_cache() {
+        let dir = env::temp_dir().join(format!("esker-link-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut cache = Cache::load(&dir, Duration::from_secs(60));
+        assert_eq!(cache.get("https://example.com"), None);
+        cache.insert("https://example.com".to_string(), LinkStatus::Broken(404));
+        cache.save().unwrap();
+
+        let cache = Cache::load(&dir, Duration::from_secs(60));
+        assert_eq!(cache.get("https://example.com"), Some(&LinkStatus::Broken(404)));
+
+        // expired entries have to be checked again.
+        let cache = Cache::load(&dir, Duration::from_secs(0));
+        assert_eq!(cache.get("https://example.com"), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_report_counts_urls() {
+        let dir = env::temp_dir().join(format!("esker-link-report-{}", std::process::id()));
+        let mut cache = Cache::load(&dir, Duration::from_secs(60));
+        cache.insert("https://gone.example".to_string(), LinkStatus::Broken(404));
+        cache.insert("https://ok.example".to_string(), LinkStatus::Ok(200));
+
+        let link = |url: &str, page: &str| {
+            let mut link = Link::empty();
+            link.url = url.to_string();
+            link.originating_file_url = Some(page.to_string());
+            link
+        };
+        let links = vec![
+            link("https://gone.example", "/a.html"),
+            link("https://gone.example", "/b.html"),
+            link("https://ok.example", "/a.html"),
+        ];
+        assert_eq!(report(&links, &cache), 1);
+    }
+}
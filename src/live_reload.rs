@@ -0,0 +1,158 @@
+//! Live reload for `esker watch`: every html page served gets a small script that listens
+//! for server sent events, which are sent once a change has been rebuilt. Changes to css in
+//! `public/` only swap the stylesheets, anything else reloads the page.
+
+use axum::{
+    body::{boxed, Full, HttpBody},
+    http::{header, Request},
+    middleware::Next,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+};
+use crate::errors::Diagnostic;
+use futures_util::stream::{self, Stream};
+use std::convert::Infallible;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// where the page listens for reloads.
+pub const EVENTS_PATH: &str = "/__esker/live-reload";
+
+const SCRIPT: &str = r#"<script>
+  (() => {
+    const events = new EventSource("/__esker/live-reload");
+    events.addEventListener("reload", () => location.reload());
+    events.addEventListener("css", () => {
+      for (const link of document.querySelectorAll('link[rel="stylesheet"]')) {
+        const url = new URL(link.href);
+        url.searchParams.set("esker-reload", Date.now());
+        link.href = url.toString();
+      }
+    });
+  })();
+</script>
+"#;
+
+/// What the browser should do after a rebuild.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Reload {
+    /// only stylesheets changed, they can be swapped without reloading.
+    Css,
+    Page,
+}
+
+impl Reload {
+    fn event_name(&self) -> &'static str {
+        match self {
+            Reload::Css => "css",
+            Reload::Page => "reload",
+        }
+    }
+}
+
+/// the stream of reload events sent to a page.
+pub fn events(receiver: broadcast::Receiver<Reload>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(reload) => {
+                    let event = Event::default().event(reload.event_name()).data("");
+                    return Some((Ok(event), receiver));
+                }
+                // reloading once is enough, no matter how many rebuilds were missed.
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+/// middleware adding the live reload script to every html page served.
+pub async fn inject_script<B>(req: Request<B>, next: Next<B>) -> Response {
+    let res = next.run(req).await;
+    let is_html = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("text/html"));
+
+    if !is_html {
+        return res;
+    }
+
+    let (mut parts, mut body) = res.into_parts();
+    let mut html = Vec::new();
+    while let Some(chunk) = body.data().await {
+        match chunk {
+            Ok(chunk) => html.extend_from_slice(&chunk),
+            Err(e) => return (parts.status, format!("Unhandled internal error: {}", e)).into_response(),
+        }
+    }
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    let html = with_script(&String::from_utf8_lossy(&html));
+    Response::from_parts(parts, boxed(Full::from(html)))
+}
+
+/// shown in place of a page that couldn't be rendered while watching; the live reload
+/// script is added like on any other page, so it goes away once the problem is fixed.
+pub fn error_page(diagnostic: &Diagnostic) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>esker: build error</title>
+</head>
+<body style="margin: 0; font-family: sans-serif; background: #1d1f21; color: #e0e0e0;">
+  <div style="max-width: 48rem; margin: 4rem auto; padding: 1.5rem; border-left: 4px solid #e06c75;">
+    <h1 style="margin-top: 0; color: #e06c75; font-size: 1.25rem;">This page couldn't be built</h1>
+    <p><code>{}</code></p>
+    <pre style="white-space: pre-wrap;">{}</pre>
+  </div>
+</body>
+</html>
+"#,
+        html_escape::encode_text(&diagnostic.location()),
+        html_escape::encode_text(&diagnostic.message)
+    )
+}
+
+/// adds the script right before `</body>`, or at the end if there isn't one.
+fn with_script(html: &str) -> String {
+    match html.rfind("</body>") {
+        Some(position) => format!("{}{}{}", &html[..position], SCRIPT, &html[position..]),
+        None => format!("{}{}", html, SCRIPT),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_script() {
+        let html = with_script("<html><body><p>hi</p></body></html>");
+        assert!(html.starts_with("<html><body><p>hi</p><script>"));
+        assert!(html.ends_with("</script>\n</body></html>"));
+
+        assert!(with_script("<p>no body</p>").ends_with("</script>\n"));
+    }
+
+    #[test]
+    fn test_error_page() {
+        let diagnostic = Diagnostic::new(
+            crate::errors::Kind::TemplateRender,
+            "notes/a.md".to_string(),
+            None,
+            "Variable `page.<x>` not found".to_string(),
+        );
+        let html = with_script(&error_page(&diagnostic));
+        assert!(html.contains("<code>notes/a.md</code>"));
+        assert!(html.contains("Variable `page.&lt;x&gt;` not found"));
+        assert!(html.contains(EVENTS_PATH));
+    }
+}
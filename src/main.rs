@@ -4,23 +4,37 @@
 #![allow(unused_variables)]
 
 pub mod config;
+pub mod dependencies;
 pub mod errors;
+pub mod feeds;
 pub mod frontmatter;
 pub mod link;
+pub mod link_checker;
+pub mod live_reload;
 pub mod md_file;
 pub mod new_site;
 pub mod parser;
+pub mod redirects;
 pub mod site;
+pub mod sitemap;
 pub mod templates;
 pub mod util;
 
-use axum::{http::StatusCode, routing::get_service, Router};
+use axum::{
+    http::StatusCode,
+    middleware,
+    routing::{get, get_service},
+    Router,
+};
 use clap::{Parser, Subcommand};
 use colored::*;
+use errors::DiagnosticsFormat;
 use hotwatch::Hotwatch;
 use parser::syntax_highlight::dump_syntax_binary;
 use site::Site;
+use link_checker::LinkChecker;
 use std::{net::SocketAddr, path::PathBuf, thread, time::Duration};
+use tokio::sync::broadcast;
 use tower_http::services::ServeDir;
 
 #[derive(Parser, Debug)]
@@ -42,7 +56,26 @@ pub struct Cli {
 #[derive(Subcommand, Debug, Clone)]
 pub enum Commands {
     /// Compile your site to /_esker/_site
-    Build,
+    Build {
+        /// Exit with an error if any internal links are broken
+        #[arg(long)]
+        strict: bool,
+        /// How to print errors and warnings
+        #[arg(long, value_enum, default_value_t = DiagnosticsFormat::Human)]
+        diagnostics_format: DiagnosticsFormat,
+    },
+    /// Check that the external links in your site still work
+    CheckLinks {
+        /// How many links to check at the same time
+        #[arg(long, default_value_t = 8)]
+        concurrency: usize,
+        /// Seconds to wait for a site to respond
+        #[arg(long, default_value_t = 10)]
+        timeout: u64,
+        /// Hours before a link is checked again, instead of using the cached result
+        #[arg(long, default_value_t = 24)]
+        ttl: u64,
+    },
     #[command(hide = true)]
     DumpSyntax,
     /// Create a new _esker site in your directory.
@@ -62,11 +95,36 @@ async fn main() {
         Some(Commands::Watch { port }) => watch(Commands::Watch { port: *port }, cli).await,
         Some(Commands::New) => new_site::init(cli.dir),
         Some(Commands::DumpSyntax) => dump_syntax_binary(),
-        Some(Commands::Build) => {
-            let mut site = Site::new(Commands::Build, cli);
+        Some(Commands::Build { strict, diagnostics_format }) => {
+            let (strict, diagnostics_format) = (*strict, *diagnostics_format);
+            let mut site = Site::new(Commands::Build { strict, diagnostics_format }, cli);
+            site.build();
+
+            // json goes to stdout on its own, so it can be piped into other tools.
+            let human = diagnostics_format == DiagnosticsFormat::Human;
+            if strict && site.errors.has_broken_links() {
+                if human {
+                    println!("{}: your site has broken links.", " Failed ".yellow().on_black());
+                }
+                util::exit();
+            }
+            if human {
+                println!("{}: site built!", " Success".green().on_black());
+            }
+        }
+        Some(Commands::CheckLinks { concurrency, timeout, ttl }) => {
+            let (concurrency, timeout, ttl) = (*concurrency, *timeout, *ttl);
+            let mut site = Site::new(Commands::CheckLinks { concurrency, timeout, ttl }, cli);
             site.build();
-            println!("{}: site built!", " Success".green().on_black());
 
+            let checker = LinkChecker::new(Duration::from_secs(timeout), concurrency);
+            let broken = link_checker::run(&site, &checker, Duration::from_secs(ttl * 60 * 60));
+
+            if broken > 0 {
+                println!("{}: your site has broken external links.", " Failed ".yellow().on_black());
+                util::exit();
+            }
+            println!("{}: all external links work!", " Success".green().on_black());
         }
         None => {}
     }
@@ -76,6 +134,8 @@ async fn watch(cmd: Commands, cli: Cli) {
     let mut site = Site::new(cmd.clone(), cli);
     site.build();
     let output_directory = site.dir_esker_site.clone();
+    let (reload_sender, _) = broadcast::channel(16);
+    let sender = reload_sender.clone();
 
     tokio::task::spawn_blocking(move || {
         let mut hotwatch =
@@ -83,7 +143,10 @@ async fn watch(cmd: Commands, cli: Cli) {
 
         hotwatch
             .watch(".", move |event| {
-                site.handle_watch_event(event);
+                if let Some(reload) = site.handle_watch_event(event) {
+                    // no one might be listening, which is fine.
+                    let _ = sender.send(reload);
+                }
             })
             .expect("failed");
 
@@ -92,15 +155,21 @@ async fn watch(cmd: Commands, cli: Cli) {
         }
     });
 
-    let app = Router::new().fallback_service(
-        get_service(ServeDir::new(output_directory).append_index_html_on_directories(true))
-            .handle_error(|error: std::io::Error| async move {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Unhandled internal error: {}", error),
-                )
-            }),
-    );
+    let app = Router::new()
+        .route(
+            live_reload::EVENTS_PATH,
+            get(move || async move { live_reload::events(reload_sender.subscribe()) }),
+        )
+        .fallback_service(
+            get_service(ServeDir::new(output_directory).append_index_html_on_directories(true))
+                .handle_error(|error: std::io::Error| async move {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Unhandled internal error: {}", error),
+                    )
+                }),
+        )
+        .layer(middleware::from_fn(live_reload::inject_script));
 
     if let Commands::Watch { port } = cmd {
         let addr = SocketAddr::from(([127, 0, 0, 1], port));
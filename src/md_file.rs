@@ -1,32 +1,44 @@
 use std::collections::HashMap;
-use std::{fs, path::PathBuf};
+use std::{fs, path::{Path, PathBuf}};
 
-use crate::frontmatter::Frontmatter;
+use crate::dependencies::Dependencies;
+use crate::errors::Errors;
+use crate::live_reload;
+use crate::Commands;
+use crate::frontmatter::{Frontmatter, FrontmatterFormat};
 use crate::link::Link;
-use crate::parser;
+use crate::parser::{self, ParsedMarkdown};
 use crate::site::Site;
-use crate::templates;
+use crate::templates::{self, pagination_path, Paginator, SectionNode};
+use crate::util;
 use slugify::slugify;
-use std::io;
-use std::io::{BufRead, BufReader};
 use tera::Context;
 
 #[derive(Debug, Clone)]
 pub struct MdFile {
     pub raw: String,
     pub html: String,
-    path: PathBuf,
+    pub path: PathBuf,
     pub web_path_parents: PathBuf,
+    /// where the page is in _site, and so its url: notes/my-note.html
     pub web_path: PathBuf,
-    out_path: PathBuf,
+    pub out_path: PathBuf,
+    /// where the redirects from the page's aliases go in _site.
+    pub aliases: Vec<PathBuf>,
     pub frontmatter: Frontmatter,
+    /// the tags from the frontmatter, followed by the ones written inline (#tag) in the file.
+    pub tags: Vec<String>,
     pub full_url: String,
     /// if file is a _index.md, we say it's a section, which
     /// is given a different tera context to render.
     pub is_section: bool,
+    /// how many lines of frontmatter were stripped from the top of `raw`.
+    frontmatter_lines: usize,
     pub backlinks: Vec<Link>,
     pub toc: Vec<Link>,
     pub related_files: Vec<Link>,
+    /// what the page links to and embeds, collected when parsing.
+    pub dependencies: Dependencies,
 }
 
 impl MdFile {
@@ -40,52 +52,93 @@ impl MdFile {
         let web_path_parents = crate::util::strip_pwd(&site.dir, &path);
 
         let filename = path.file_stem().unwrap().to_str().unwrap().to_string();
-        let mut out_file_path_slugified = slugify!(&filename);
-        // takes slugified file name and adds html extension
-        let web_path_stem = PathBuf::from(out_file_path_slugified).with_extension("html");
-        let out_path = PathBuf::from(&site.dir_esker_site)
-            .join(web_path_parents.join(PathBuf::from(&web_path_stem)));
+        let is_section = filename == "_index";
+        let clean_urls = site.clean_urls();
+
+        // a permalink puts the page anywhere in the site, a slug renames it in its folder
+        // (or renames the folder, for a section). Sections are the index.html of theirs.
+        let permalink = fm.permalink.as_deref().map(url_parts).filter(|parts| !parts.is_empty());
+        let slug = fm.slug.as_deref().map(|slug| slugify!(slug)).filter(|slug| !slug.is_empty());
+        let web_path = match (permalink, slug) {
+            (Some(parts), _) if is_section => parts.iter().collect::<PathBuf>().join("index.html"),
+            (Some(mut parts), _) => {
+                let stem = parts.pop().unwrap_or_default();
+                page_path(&parts.iter().collect::<PathBuf>(), &stem, clean_urls)
+            }
+            (None, Some(slug)) if is_section => match web_path_parents.parent() {
+                Some(parent) => parent.join(slug).join("index.html"),
+                None => PathBuf::from("index.html"),
+            },
+            (None, Some(slug)) => page_path(&web_path_parents, &slug, clean_urls),
+            (None, None) if is_section => web_path_parents.join("index.html"),
+            (None, None) => page_path(&web_path_parents, &slugify!(&filename), clean_urls),
+        };
+        let out_path = PathBuf::from(&site.dir_esker_site).join(&web_path);
 
         // now let's make the full url.
-        let url_path = web_path_parents
-            .join(web_path_stem.clone())
-            .into_os_string()
-            .into_string()
-            .unwrap();
-        let full_url = site.build_with_baseurl(url_path);
-        // end bad code byeeee
+        let full_url = site.build_page_url(&web_path);
+
+        // aliases are old names of the note (in its folder), or old urls (from the top of the
+        // site, starting with a /); each gets a page that redirects here.
+        let aliases = fm
+            .aliases
+            .iter()
+            .filter_map(|alias| {
+                let mut parts = url_parts(alias);
+                let stem = parts.pop()?;
+                let mut dir = match alias.starts_with('/') {
+                    true => PathBuf::new(),
+                    false => web_path_parents.clone(),
+                };
+                dir.extend(parts);
+                // an old url is kept as it was written: /notes/old.html, or /notes/old/
+                Some(match (alias.ends_with('/'), alias.ends_with(".html")) {
+                    (true, _) => page_path(&dir, &stem, true),
+                    (_, true) => page_path(&dir, &stem, false),
+                    _ => page_path(&dir, &stem, clean_urls),
+                })
+            })
+            .filter(|alias| *alias != web_path)
+            .collect();
 
         let mut md_file = MdFile {
             raw: raw_str,
             html: String::from(""),
             path,
             web_path_parents,
-            web_path: web_path_stem,
+            web_path,
             out_path,
+            aliases,
+            tags: Vec::new(),
             frontmatter: fm,
             full_url,
-            is_section: filename == "_index",
+            is_section,
+            frontmatter_lines: 0,
             backlinks: Vec::new(),
             toc: Vec::new(),
             related_files: Vec::new(),
+            dependencies: Dependencies::default(),
         };
 
-        md_file
-            .set_raw_contents()
-            .expect("Failed to set raw contents for file");
+        md_file.set_raw_contents();
+        md_file.set_tags();
 
         md_file
     }
 
     /// collect links, tags, etc so that they are available the next pass when we render.
-    pub fn parse_markdown_to_html(&mut self, site: &mut Site) {
-        let parsed_str = parser::new(self, site);
-        self.html = parsed_str;
+    /// The links (and errors) found are returned, to be added to the site.
+    pub fn parse_markdown_to_html(&mut self, site: &Site) -> ParsedMarkdown {
+        self.toc.clear();
+        self.dependencies.clear();
+        let mut parsed = parser::new(self, site);
+        self.html = std::mem::take(&mut parsed.html);
+        parsed
     }
 
-    fn get_related_files(&mut self, site: &Site) {
+    pub fn get_related_files(&mut self, site: &Site) {
         let mut related_files: Vec<Link> = Vec::new();
-        for tag in &self.frontmatter.tags {
+        for tag in &self.tags {
             if let Some(tags) = site.tags.get(tag) {
                 for tag_link in tags {
                     if tag_link.url != self.full_url && !related_files.contains(tag_link) {
@@ -99,91 +152,221 @@ impl MdFile {
 
     /// enables creating "post list" type pages where the "section" context
     /// corresponds to every file in the dir. Only runs for files named _index.md in a dir.
+    /// With `paginate_by` (in the file's frontmatter, or the config) the list is split up:
+    /// posts/index.html gets the newest pages, posts/page/2/index.html the next ones, etc.
     pub fn write_section_html(
-        &mut self,
+        &self,
         site: &Site,
         markdown_files: &HashMap<PathBuf, Vec<MdFile>>,
+        errors: &mut Errors,
     ) {
         if let Some(section_content) = markdown_files.get(&self.web_path_parents) {
-            let serialized_pages: Vec<_> = section_content
+            let mut section_files: Vec<&MdFile> = section_content
                 .iter()
                 .filter(|md_file| !md_file.is_section)
-                .map(templates::Page::new)
                 .collect();
+            section_files.sort_by(|a, b| MdFile::newest_first(a, b));
+            let serialized_pages: Vec<_> = section_files.into_iter().map(templates::Page::new).collect();
+
+            let paginate_by = self.frontmatter.paginate_by.or(site.config.paginate_by);
+            let url = |current: usize| match current {
+                1 => self.full_url.clone(),
+                n => site.build_page_url(&pagination_path(self.web_path.parent().unwrap_or(Path::new("")), n)),
+            };
+            let paginators = Paginator::paginate(&serialized_pages, paginate_by, url);
+            let out_dir = self.out_path.parent().unwrap_or(&site.dir_esker_site);
+            let no_section = SectionNode::default();
+            let section = self.section(site).unwrap_or(&no_section);
+
+            for paginator in &paginators {
+                let mut ctx = Context::new();
+                ctx.insert("page", &templates::Page::new(self));
+                ctx.insert("pages", paginator.pages);
+                ctx.insert("paginator", paginator);
+                ctx.insert("baseurl", &site.config.url.clone());
+                ctx.insert("section", &templates::SectionPage::new(paginator.pages, section));
+                ctx.insert("section_tree", &site.section_tree);
+                ctx.insert("config", &templates::Config::new(site));
+                ctx.insert("tags", &site.tags);
+                ctx.insert("tag_tree", &site.tag_tree);
+                ctx.insert("tag_list", &site.tag_list);
+                ctx.insert("sitemap", &site.template_sitemap);
 
-            self.get_related_files(site);
-            let mut ctx = Context::new();
-            ctx.insert("page", &templates::Page::new(self));
-            ctx.insert("pages", &serialized_pages);
-            ctx.insert("baseurl", &site.config.url.clone());
-            ctx.insert("section", &templates::SectionPage::new(serialized_pages));
-            ctx.insert("config", &templates::Config::new(site));
-            ctx.insert("tags", &site.tags);
-            ctx.insert("tags", &site.tags);
-            ctx.insert("sitemap", &site.template_sitemap);
-
-            let template_name = templates::get_name(&site.tera, &self.frontmatter.template);
-            let rendered_template = site.tera.render(&template_name, &ctx).unwrap();
-            let prefix = &self.out_path.parent().unwrap();
-            fs::create_dir_all(prefix).unwrap();
-            fs::write(&self.out_path, rendered_template).expect("Unable to write file");
+                match paginator.current {
+                    1 => self.render(site, &ctx, &self.out_path, errors),
+                    n => self.render(site, &ctx, &pagination_path(out_dir, n), errors),
+                }
+            }
+            remove_pages_after(out_dir, paginators.len());
         }
     }
 
     /// writes a file to it's specified output path.
-    pub fn write_html(&mut self, site: &mut Site) {
-        self.get_related_files(site);
-
+    pub fn write_html(&self, site: &Site, errors: &mut Errors) {
         let mut ctx = Context::new();
         ctx.insert("page", &templates::Page::new(self));
         ctx.insert("baseurl", &site.config.url.clone());
+        ctx.insert("section", &self.section(site));
+        ctx.insert("section_tree", &site.section_tree);
         ctx.insert("tags", &site.tags);
+        ctx.insert("tag_tree", &site.tag_tree);
+        ctx.insert("tag_list", &site.tag_list);
         ctx.insert("config", &templates::Config::new(site));
         ctx.insert("sitemap", &site.template_sitemap);
+        self.render(site, &ctx, &self.out_path, errors);
+    }
+
+    /// the section (folder) the file is in.
+    fn section<'a>(&self, site: &'a Site) -> Option<&'a SectionNode> {
+        site.section_tree.find(&util::path_to_string(&self.web_path_parents))
+    }
+
+    /// renders the file's template and writes it out; the page is skipped if rendering fails.
+    fn render(&self, site: &Site, ctx: &Context, out_path: &Path, errors: &mut Errors) {
         let template_name = templates::get_name(&site.tera, &self.frontmatter.template);
-        let rendered_template = site.tera.render(&template_name, &ctx).unwrap();
+        let rendered_template = match site.tera.render(&template_name, ctx) {
+            Ok(rendered_template) => rendered_template,
+            Err(e) => {
+                errors.add_template_error(self.frontmatter.get_filepath_as_str(), &e);
+                // while watching, the page shows what went wrong until it's fixed.
+                match (&site.cli_command, errors.diagnostics().last()) {
+                    (Commands::Watch { .. }, Some(diagnostic)) => live_reload::error_page(diagnostic),
+                    _ => return,
+                }
+            }
+        };
 
-        let prefix = &self.out_path.parent().unwrap();
-        fs::create_dir_all(prefix).unwrap();
-        fs::write(&self.out_path, rendered_template).expect("Unable to write file");
+        site.write_file(out_path, rendered_template, errors);
     }
 
     pub fn get_backlinks_for_file(&mut self, site: &Site) {
         let mut out: Vec<Link> = Vec::new();
         for g_link in &site.links.internal {
             if let Some(originating_file_url) = &g_link.originating_file_url {
-
-                if g_link.url == self.full_url && self.full_url != originating_file_url.clone() && !out.contains(g_link) {
-                    out.push(g_link.clone());
+                // the same link on different lines is still one backlink.
+                let g_link = Link { line: None, ..g_link.clone() };
+                if g_link.url_without_fragment() == self.full_url && self.full_url != originating_file_url.clone() && !out.contains(&g_link) {
+                    out.push(g_link);
                 }
             }
         }
         self.backlinks = out
     }
 
-    /// sets the "raw" contents field for the md_file to be the file without the frontmatter.
-    fn set_raw_contents(&mut self) -> io::Result<()> {
-        let input_file = fs::File::open(self.path.clone())?;
-        let mut output: Vec<_> = Vec::new();
-        let mut reader = BufReader::new(input_file);
-        let mut in_frontmatter = false;
-
-        for (_i, line) in reader.lines().enumerate() {
-            // Write the line to the output file
-            let line = line?;
-            if line == "---" && !in_frontmatter {
-                in_frontmatter = true
-            } else if line == "---" && in_frontmatter {
-                in_frontmatter = false;
-                continue;
+    /// orders pages newest first (by url too, so pages created at the same time keep their place).
+    pub fn newest_first(a: &MdFile, b: &MdFile) -> std::cmp::Ordering {
+        b.frontmatter
+            .date_created
+            .cmp(&a.frontmatter.date_created)
+            .then_with(|| a.full_url.cmp(&b.full_url))
+    }
+
+    /// moves the page (and so its url) to `web_path` in _site.
+    pub fn set_web_path(&mut self, site: &Site, web_path: PathBuf) {
+        self.out_path = site.dir_esker_site.join(&web_path);
+        self.full_url = site.build_page_url(&web_path);
+        self.web_path = web_path;
+    }
+
+    /// the file's path relative to the vault, without the extension: posts/first_post
+    pub fn vault_path(&self) -> PathBuf {
+        self.web_path_parents.join(self.path.file_stem().unwrap())
+    }
+
+    /// the line in the original file for a byte offset into `raw`, for error reporting.
+    pub fn line_number(&self, offset: usize) -> usize {
+        let preceding = self.raw.get(..offset).unwrap_or(&self.raw);
+        self.frontmatter_lines + preceding.matches('\n').count() + 1
+    }
+
+    fn set_tags(&mut self) {
+        let mut tags = self.frontmatter.tags.clone();
+        for tag in parser::tags::find(&self.raw) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
             }
+        }
+        self.tags = tags;
+    }
+
+    /// sets the "raw" contents field for the md_file to be the file without the frontmatter.
+    fn set_raw_contents(&mut self) {
+        let contents = std::mem::take(&mut self.raw);
+        let mut lines = contents.lines();
 
-            if !in_frontmatter {
-                output.push(line)
+        if let Some(format) = FrontmatterFormat::detect(&contents) {
+            // the opening delimiter, then everything up to (and including) the closing one.
+            lines.next();
+            self.frontmatter_lines = 1;
+            for line in lines.by_ref() {
+                self.frontmatter_lines += 1;
+                if line == format.delimiter() {
+                    break;
+                }
             }
         }
 
-        self.raw = output.join("\n");
-        Ok(())
+        self.raw = lines.collect::<Vec<_>>().join("\n");
+    }
+}
+
+/// where a page at `dir`/`stem` is written in _site: notes/my-note.html, or
+/// notes/my-note/index.html with clean urls.
+fn page_path(dir: &Path, stem: &str, clean_urls: bool) -> PathBuf {
+    match clean_urls {
+        true => dir.join(stem).join("index.html"),
+        false => dir.join(format!("{}.html", stem)),
+    }
+}
+
+/// the same place in _site with a number on the end: notes/my-note.html -> notes/my-note-2.html
+/// Pages that are the index.html of their own directory (sections, and pages with clean
+/// urls) number the directory: notes/my-note/index.html -> notes/my-note-2/index.html
+pub fn with_suffix(web_path: &Path, n: usize, own_dir: bool) -> PathBuf {
+    let parent = web_path.parent().unwrap_or(Path::new(""));
+    let stem = web_path.file_stem().unwrap_or_default().to_string_lossy();
+    match (own_dir, parent.file_name(), parent.parent()) {
+        (true, Some(dir), Some(grandparent)) => grandparent
+            .join(format!("{}-{}", dir.to_string_lossy(), n))
+            .join("index.html"),
+        _ => parent.join(format!("{}-{}.html", stem, n)),
+    }
+}
+
+/// a permalink or alias as the parts of a path in _site, each slugged like file names are:
+/// "/Essays/Why Rust.html" -> ["essays", "why-rust"]. Empty parts (and "..") are left out.
+fn url_parts(url: &str) -> Vec<String> {
+    let url = url.trim_end_matches('/');
+    let url = url.strip_suffix(".html").unwrap_or(url);
+    url.split('/').map(|part| slugify!(part)).filter(|part| !part.is_empty()).collect()
+}
+
+/// removes the pages of a paginated list past the last one, left from when it was longer.
+pub fn remove_pages_after(dir: &Path, last: usize) {
+    let mut current = last + 1;
+    while fs::remove_file(pagination_path(dir, current)).is_ok() {
+        // (and its directory, page/<n>/, unless something else is in it)
+        let _ = fs::remove_dir(dir.join("page").join(current.to_string()));
+        current += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::with_suffix;
+
+    #[test]
+    fn test_with_suffix() {
+        let page = Path::new("notes/my-note.html");
+        assert_eq!(with_suffix(page, 2, false), PathBuf::from("notes/my-note-2.html"));
+        assert_eq!(with_suffix(Path::new("my-note.html"), 3, false), PathBuf::from("my-note-3.html"));
+
+        let own_dir = Path::new("notes/my-note/index.html");
+        assert_eq!(with_suffix(own_dir, 2, true), PathBuf::from("notes/my-note-2/index.html"));
+        assert_eq!(with_suffix(Path::new("my-note/index.html"), 2, true), PathBuf::from("my-note-2/index.html"));
+        // the site's own index.html has no directory to number.
+        assert_eq!(with_suffix(Path::new("index.html"), 2, true), PathBuf::from("index-2.html"));
     }
 }
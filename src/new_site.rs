@@ -22,6 +22,33 @@ ignored_directories: ["dailies", "jots", "work", "media", "templates"]
 # the url you wish to use for grouping "tags" under
 tags_url: "tags"
 
+# split the list of pages on section (_index.md) and tag pages up into pages of this many.
+# A section can set its own with paginate_by in its frontmatter.
+# paginate_by: 20
+
+# write a robots.txt that points search engines to your sitemap.xml
+# robots_txt: true
+
+# write pages as my-note/index.html and link to them as /my-note/, without ".html".
+# clean_urls: true
+
+# notes that end up at the same url ("My Note.md" and "my-note.md" are both my-note.html)
+# are skipped, except for the first one; set this to give them one of their own instead
+# (my-note-2.html).
+# disambiguate_urls: true
+
+# give folders with pages but no _index.md a page listing them (from list.html), so their
+# url (/posts/) works too.
+# auto_index: true
+
+# atom.xml and feed.json: whole pages or only their summary, how many of the newest pages,
+# and whether each section (a folder with an _index.md) and tag gets its own feeds.
+# feeds:
+#   content: "full"
+#   limit: 20
+#   sections: true
+#   tags: true
+
 
 # Add the name of a theme if you are using
 # to use a theme, you must have it in your themes directory, for ex:
@@ -124,15 +151,22 @@ pub const LIST_HTML: &str = r#"{% extends "base.html" %}
 
 {% block content %}
   {{super()}}
-  <ol reversed>
-      {% for page in section.pages | sort(attribute="date_created_timestamp") %}
+  <ul>
+      {% for page in paginator.pages %}
         <li>
           <a href="{{page.url}}"> <h3>{{page.title}}</h3> </a>
           <i>{{page.summary}}</i>
           <div class="text-sm text-alt">{{page.date_created}}</div>
         </li>
       {% endfor %}
-  </ol>
+  </ul>
+  {% if paginator.total > 1 %}
+  <nav class="pagination">
+    {% if paginator.prev_url %}<a href="{{paginator.prev_url}}">Newer</a>{% endif %}
+    <span>{{paginator.current}} / {{paginator.total}}</span>
+    {% if paginator.next_url %}<a href="{{paginator.next_url}}">Older</a>{% endif %}
+  </nav>
+  {% endif %}
 
 {% endblock content %}
 "#;
@@ -169,14 +203,44 @@ pub const RSS_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
 pub const TAGS_HTML: &str = r#"{% extends "base.html" %}
 {% block title %} Tags {% endblock title %}
 {% block content %}
-      <h3>{{tag}}</h3>
+      <h3>{% if tag %}#{{tag}}{% else %}Tags{% endif %}</h3>
+      {% if tag_node.children %}
+      <ul class="tag-children">
+      {% for child in tag_node.children %}
+        <li>
+          <a href={{child.url}}>#{{child.tag}}</a>
+        </li>
+      {% endfor %}
+      </ul>
+      {% endif %}
       <ul>
-      {% for link_tag in tags[tag] %}
+      {% for link_tag in paginator.pages %}
         <li>
           <a href={{link_tag.url}}>{{link_tag.title}} </a>
         </li>
       {% endfor %}
       </ul>
+      {% if paginator.total > 1 %}
+      <nav class="pagination">
+        {% if paginator.prev_url %}<a href="{{paginator.prev_url}}">Previous</a>{% endif %}
+        <span>{{paginator.current}} / {{paginator.total}}</span>
+        {% if paginator.next_url %}<a href="{{paginator.next_url}}">Next</a>{% endif %}
+      </nav>
+      {% endif %}
+{% endblock content %}
+"#;
+
+pub const TAGS_INDEX_HTML: &str = r#"{% extends "base.html" %}
+{% block title %} Tags {% endblock title %}
+{% block content %}
+      <h3>Tags</h3>
+      <ul class="tag-list">
+      {% for tag_info in tag_list %}
+        <li>
+          <a href={{tag_info.url}}>#{{tag_info.tag}}</a> ({{tag_info.count}})
+        </li>
+      {% endfor %}
+      </ul>
 {% endblock content %}
 "#;
 
@@ -289,6 +353,39 @@ ul, ol {
 
 img { max-width: 100%; }
 
+.embed {
+  border-left: 2px solid var(--border-col);
+  padding-left: 16px;
+  margin: 16px 0;
+}
+
+.callout {
+  --callout-color: 41, 128, 185;
+  border-left: 4px solid rgb(var(--callout-color));
+  background-color: rgba(var(--callout-color), 0.1);
+  padding: 12px 16px;
+  margin: 16px 0;
+}
+
+.callout-title {
+  font-weight: bold;
+  color: rgb(var(--callout-color));
+}
+
+summary.callout-title {
+  cursor: pointer;
+}
+
+.callout-content > :last-child {
+  margin-bottom: 0;
+}
+
+.callout-tip, .callout-success { --callout-color: 39, 174, 96; }
+.callout-question, .callout-warning { --callout-color: 230, 126, 34; }
+.callout-failure, .callout-danger, .callout-bug { --callout-color: 231, 76, 60; }
+.callout-example { --callout-color: 142, 68, 173; }
+.callout-quote { --callout-color: 127, 140, 141; }
+
 .text-sm {font-size: 12px;}
 .text-md {font-size: 16px;}
 
@@ -363,6 +460,7 @@ pub fn init(dir: Option<PathBuf>) {
         files.insert(String::from("templates/base.html"), BASE_HTML);
         files.insert(String::from("templates/single.html"), SINGLE_HTML);
         files.insert(String::from("templates/tags.html"), TAGS_HTML);
+        files.insert(String::from("templates/tags_index.html"), TAGS_INDEX_HTML);
         files.insert(String::from("templates/list.html"), LIST_HTML);
         files.insert(String::from("templates/feed.rss"), RSS_XML);
         files.insert(String::from("config.yaml"), CONFIG_YAML);
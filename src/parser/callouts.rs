@@ -0,0 +1,232 @@
+//! Obsidian callouts: blockquotes starting with `[!type]`, an optional `+`/`-` to make them
+//! foldable (open or closed) and an optional title.
+//!
+//! > [!warning]- Careful
+//! > the body of the callout
+
+use std::collections::VecDeque;
+
+use lazy_static::lazy_static;
+use pulldown_cmark::{html::push_html, CowStr, Event, Tag};
+use regex::Regex;
+
+lazy_static! {
+    static ref CALLOUT: Regex = Regex::new(r"^\[!([\w-]+)\]([+-]?)\s*").unwrap();
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Callout {
+    /// the type as written, for a default title and the data-callout attribute.
+    kind: String,
+    /// None if the callout can't be folded, otherwise whether it starts open.
+    open: Option<bool>,
+}
+
+impl Callout {
+    // types obsidian treats as another, so they look the same.
+    fn class(&self) -> &str {
+        match self.kind.as_str() {
+            "summary" | "tldr" => "abstract",
+            "hint" | "important" => "tip",
+            "check" | "done" => "success",
+            "help" | "faq" => "question",
+            "caution" | "attention" => "warning",
+            "fail" | "missing" => "failure",
+            "error" => "danger",
+            "cite" => "quote",
+            kind => kind,
+        }
+    }
+
+    fn default_title(&self) -> String {
+        let mut chars = self.kind.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        }
+    }
+
+    fn opening_html(&self, title: &str) -> String {
+        let aside = format!(
+            r#"<aside class="callout callout-{}" data-callout="{}">"#,
+            html_escape::encode_double_quoted_attribute(self.class()),
+            html_escape::encode_double_quoted_attribute(&self.kind)
+        );
+        match self.open {
+            None => format!(r#"{}<div class="callout-title">{}</div><div class="callout-content">"#, aside, title),
+            Some(open) => format!(
+                r#"{}<details{}><summary class="callout-title">{}</summary><div class="callout-content">"#,
+                aside,
+                if open { " open" } else { "" },
+                title
+            ),
+        }
+    }
+
+    fn closing_html(&self) -> &'static str {
+        match self.open {
+            None => "</div></aside>",
+            Some(_) => "</div></details></aside>",
+        }
+    }
+}
+
+pub struct ParseCallouts<'a, I: Iterator<Item = Event<'a>>> {
+    parent: I,
+    /// events read ahead while checking for a callout, to be handed out (again) first.
+    queue: VecDeque<Event<'a>>,
+    /// one entry per open blockquote, Some if it's a callout.
+    blockquotes: Vec<Option<Callout>>,
+}
+
+impl<'a, I: Iterator<Item = Event<'a>>> ParseCallouts<'a, I> {
+    pub fn new(parent: I) -> Self {
+        Self {
+            parent,
+            queue: VecDeque::new(),
+            blockquotes: Vec::new(),
+        }
+    }
+
+    fn next_event(&mut self) -> Option<Event<'a>> {
+        self.queue.pop_front().or_else(|| self.parent.next())
+    }
+
+    /// called after a blockquote starts: if its first line is a callout marker, the line is
+    /// replaced by the callout's opening html. Otherwise the events read are put back.
+    fn start_blockquote(&mut self) -> Event<'a> {
+        let mut read = Vec::new();
+        // the first line of the blockquote, and what ended it.
+        let mut line = Vec::new();
+        let mut line_end = None;
+
+        match self.next_event() {
+            Some(Event::Start(Tag::Paragraph)) => read.push(Event::Start(Tag::Paragraph)),
+            Some(event) => {
+                self.queue.push_back(event);
+                return self.not_a_callout(read);
+            }
+            None => return self.not_a_callout(read),
+        }
+        while let Some(event) = self.next_event() {
+            read.push(event.clone());
+            match event {
+                Event::SoftBreak | Event::HardBreak | Event::End(Tag::Paragraph) => {
+                    line_end = Some(event);
+                    break;
+                }
+                event => line.push(event),
+            }
+        }
+
+        // the marker is split up into several text events ("[", "!note", "]").
+        let leading_text: String = line
+            .iter()
+            .map_while(|event| match event {
+                Event::Text(text) => Some(text.as_ref()),
+                _ => None,
+            })
+            .collect();
+        let Some(captures) = CALLOUT.captures(&leading_text) else {
+            return self.not_a_callout(read);
+        };
+        let callout = Callout {
+            kind: captures[1].to_lowercase(),
+            open: match &captures[2] {
+                "+" => Some(true),
+                "-" => Some(false),
+                _ => None,
+            },
+        };
+
+        // what's left of the line after the marker is the title.
+        let text_events = line.iter().take_while(|event| matches!(event, Event::Text(_))).count();
+        let mut title_events: Vec<Event> = Vec::new();
+        let rest = &leading_text[captures[0].len()..];
+        if !rest.is_empty() {
+            title_events.push(Event::Text(CowStr::from(rest.to_string())));
+        }
+        title_events.extend(line.into_iter().skip(text_events));
+        let mut title = String::new();
+        push_html(&mut title, title_events.into_iter());
+        if title.trim().is_empty() {
+            title = html_escape::encode_text(&callout.default_title()).to_string();
+        }
+
+        // the rest of the paragraph is the start of the callout's body.
+        if let Some(Event::SoftBreak | Event::HardBreak) = line_end {
+            self.queue.push_back(Event::Start(Tag::Paragraph));
+        }
+
+        let html = callout.opening_html(title.trim());
+        self.blockquotes.push(Some(callout));
+        Event::Html(html.into())
+    }
+
+    fn not_a_callout(&mut self, read: Vec<Event<'a>>) -> Event<'a> {
+        for event in read.into_iter().rev() {
+            self.queue.push_front(event);
+        }
+        self.blockquotes.push(None);
+        Event::Start(Tag::BlockQuote)
+    }
+}
+
+impl<'a, I: Iterator<Item = Event<'a>>> Iterator for ParseCallouts<'a, I> {
+    type Item = Event<'a>;
+
+    /// replaces blockquotes that are callouts with an <aside> (and <details> if they fold).
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_event()? {
+            Event::Start(Tag::BlockQuote) => Some(self.start_blockquote()),
+            Event::End(Tag::BlockQuote) => match self.blockquotes.pop().flatten() {
+                Some(callout) => Some(Event::Html(callout.closing_html().into())),
+                None => Some(Event::End(Tag::BlockQuote)),
+            },
+            event => Some(event),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::Parser;
+
+    fn render(markdown: &str) -> String {
+        let mut html = String::new();
+        push_html(&mut html, ParseCallouts::new(Parser::new(markdown)));
+        html
+    }
+
+    #[test]
+    fn test_callout() {
+        let html = render("> [!note]\n> some *text*\n> more");
+        assert_eq!(
+            html,
+            "<aside class=\"callout callout-note\" data-callout=\"note\"><div class=\"callout-title\">Note</div>\
+             <div class=\"callout-content\">\n<p>some <em>text</em>\nmore</p>\n</div></aside>"
+        );
+    }
+
+    #[test]
+    fn test_callout_title_and_folding() {
+        let html = render("> [!TLDR]- A **short** title\n>\n> body");
+        assert!(html.starts_with(
+            "<aside class=\"callout callout-abstract\" data-callout=\"tldr\"><details>\
+             <summary class=\"callout-title\">A <strong>short</strong> title</summary>"
+        ));
+        assert!(html.ends_with("<p>body</p>\n</div></details></aside>"));
+
+        assert!(render("> [!tip]+\n> body").contains("<details open><summary class=\"callout-title\">Tip</summary>"));
+    }
+
+    #[test]
+    fn test_nested_and_plain_blockquotes() {
+        let html = render("> [!info]\n> > plain quote\n> \n> > [!bug] inner\n\n> just a quote");
+        assert_eq!(html.matches("<aside").count(), 2);
+        assert_eq!(html.matches("</aside>").count(), 2);
+        assert!(html.contains("<blockquote>\n<p>plain quote</p>\n</blockquote>"));
+        assert!(html.ends_with("<blockquote>\n<p>just a quote</p>\n</blockquote>\n"));
+    }
+}
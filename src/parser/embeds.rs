@@ -0,0 +1,174 @@
+//! Helpers for obsidian embeds: ![[Note]], ![[Note#Heading]], ![[Note#^block-id]] and ![[image.png|300x200]].
+//! Embedded notes are transcluded by pulling the requested part out of the note's markdown,
+//! which is then rendered in place of the embed (see `ParseWikilinks`).
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use slugify::slugify;
+use std::path::Path;
+
+use crate::site::Site;
+
+lazy_static! {
+    static ref ATX_HEADING: Regex = Regex::new(r"^(#{1,6})\s+(.*?)\s*#*\s*$").unwrap();
+    static ref DIMENSIONS: Regex = Regex::new(r"^(\d+)(?:x(\d+))?$").unwrap();
+    static ref BLOCK_ID: Regex = Regex::new(r"(^|\s)\^[A-Za-z0-9-]+$").unwrap();
+}
+
+const IMAGE_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp", "avif"];
+
+/// attachments are linked to (or embedded) by their path in the vault, relative to the file
+/// they're in (`from_dir`) or by their name in the attachment directory.
+/// Returns the attachment's path in the vault.
+pub fn find_attachment(site: &Site, target: &str, from_dir: &Path) -> Option<String> {
+    let target = target.trim_start_matches('/');
+    let mut candidates = vec![target.to_string(), from_dir.join(target).display().to_string()];
+    if let Some(attachment_dir) = &site.config.attachment_directory {
+        candidates.push(Path::new(attachment_dir).join(target).display().to_string());
+    }
+
+    candidates.into_iter().find(|c| site.dir.join(c).is_file())
+}
+
+/// whether an embed target is a note (no extension, or .md) rather than an attachment.
+pub fn is_note(target: &str) -> bool {
+    match Path::new(target).extension() {
+        Some(ext) => ext == "md",
+        None => true,
+    }
+}
+
+pub fn is_image(target: &str) -> bool {
+    match Path::new(target).extension() {
+        Some(ext) => IMAGE_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()),
+        None => false,
+    }
+}
+
+/// parses the size of an embedded image, ex: `300` (width) or `300x200` (width x height).
+pub fn parse_dimensions(s: &str) -> Option<(u32, Option<u32>)> {
+    let caps = DIMENSIONS.captures(s.trim())?;
+    let width = caps[1].parse().ok()?;
+    let height = caps.get(2).and_then(|h| h.as_str().parse().ok());
+    Some((width, height))
+}
+
+/// removes a `^block-id` marker from the end of a line of text; they are only for embedding.
+pub fn strip_block_id(text: &str) -> &str {
+    match BLOCK_ID.find(text) {
+        Some(marker) => text[..marker.start()].trim_end(),
+        None => text,
+    }
+}
+
+/// The part of a note that an embed asks for.
+pub fn extract(markdown: &str, fragment: Option<&str>) -> Option<String> {
+    match fragment {
+        None => Some(markdown.to_string()),
+        Some(fragment) => match fragment.strip_prefix('^') {
+            Some(block_id) => extract_block(markdown, block_id),
+            None => extract_section(markdown, fragment),
+        },
+    }
+}
+
+/// returns a heading and everything under it, up until the next heading of the same (or higher) level.
+pub fn extract_section(markdown: &str, heading: &str) -> Option<String> {
+    let wanted = slugify!(heading);
+    let mut section: Vec<&str> = Vec::new();
+    let mut section_level: Option<usize> = None;
+    let mut in_fence = false;
+
+    for line in markdown.lines() {
+        if is_fence(line) {
+            in_fence = !in_fence;
+        }
+
+        let heading = if in_fence { None } else { ATX_HEADING.captures(line) };
+
+        match (section_level, heading) {
+            (None, Some(caps)) if slugify!(&caps[2]) == wanted => {
+                section_level = Some(caps[1].len());
+                section.push(line);
+            }
+            (Some(level), Some(caps)) if caps[1].len() <= level => break,
+            (Some(_), _) => section.push(line),
+            _ => (),
+        }
+    }
+
+    section_level.map(|_| section.join("\n"))
+}
+
+/// returns the block (paragraph, list, quote...) marked with `^block_id`, without the marker.
+/// Obsidian puts the marker at the end of a block, or on its own line right after one.
+pub fn extract_block(markdown: &str, block_id: &str) -> Option<String> {
+    let marker = format!("^{}", block_id);
+    let blocks: Vec<Vec<&str>> = markdown
+        .split("\n\n")
+        .map(|block| block.lines().collect::<Vec<_>>())
+        .filter(|lines| !lines.is_empty())
+        .collect();
+
+    for (i, block) in blocks.iter().enumerate() {
+        let Some(pos) = block.iter().position(|line| line.trim_end().ends_with(&marker)) else {
+            continue;
+        };
+
+        if block[pos].trim() == marker {
+            // marker on its own line, refers to whatever came before it.
+            let lines = if pos > 0 { &block[..pos] } else { blocks.get(i.checked_sub(1)?)?.as_slice() };
+            return Some(lines.join("\n"));
+        }
+
+        let mut lines: Vec<String> = block.iter().map(|l| l.to_string()).collect();
+        let line = lines[pos].trim_end();
+        lines[pos] = line[..line.len() - marker.len()].trim_end().to_string();
+        return Some(lines.join("\n"));
+    }
+
+    None
+}
+
+fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str = "intro\n\n## First\n\nfirst body ^abc\n\n### Nested\n\nnested body\n\n```\n## not a heading\n```\n\n## Second\n\n- a\n- b\n\n^list";
+
+    #[test]
+    fn test_extract_section() {
+        let res = extract_section(NOTE, "First").unwrap();
+        assert!(res.starts_with("## First"));
+        assert!(res.contains("nested body"));
+        assert!(res.contains("## not a heading"));
+        assert!(!res.contains("Second"));
+        assert_eq!(extract_section(NOTE, "Missing"), None);
+    }
+
+    #[test]
+    fn test_extract_block() {
+        assert_eq!(extract_block(NOTE, "abc"), Some("first body".to_string()));
+        assert_eq!(extract_block(NOTE, "list"), Some("- a\n- b".to_string()));
+        assert_eq!(extract_block(NOTE, "nope"), None);
+    }
+
+    #[test]
+    fn test_strip_block_id() {
+        assert_eq!(strip_block_id("some text ^abc-1"), "some text");
+        assert_eq!(strip_block_id("^abc"), "");
+        assert_eq!(strip_block_id("2^10"), "2^10");
+    }
+
+    #[test]
+    fn test_parse_dimensions() {
+        assert_eq!(parse_dimensions("300"), Some((300, None)));
+        assert_eq!(parse_dimensions("300x200"), Some((300, Some(200))));
+        assert_eq!(parse_dimensions("a caption"), None);
+    }
+}
@@ -5,11 +5,17 @@ use slugify::slugify;
 
 pub struct ParseHeadlines<'a, I: Iterator<Item = Event<'a>>> {
     parent: I,
+    with_ids: bool,
 }
 
 impl<'a, I: Iterator<Item = Event<'a>>> ParseHeadlines<'a, I> {
     pub fn new(parent: I) -> Self {
-        Self { parent }
+        Self { parent, with_ids: true }
+    }
+
+    /// for headlines of an embedded note: ids would clash with the ones on the page embedding it.
+    pub fn without_ids(parent: I) -> Self {
+        Self { parent, with_ids: false }
     }
 }
 
@@ -49,7 +55,11 @@ impl<'a, I: Iterator<Item = Event<'a>>> Iterator for ParseHeadlines<'a, I> {
 
         let mut res = String::new();
 
-        write!(res, r#"<{heading_level} id="{id}">"#).unwrap();
+        if self.with_ids {
+            write!(res, r#"<{heading_level} id="{id}">"#).unwrap();
+        } else {
+            write!(res, "<{heading_level}>").unwrap();
+        }
         res.push_str(&inner_html);
         write!(res, "</{heading_level}>").unwrap();
 
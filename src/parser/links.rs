@@ -0,0 +1,728 @@
+//! Handles obsidian style [[wikilinks]]: [[Note]], [[folder/Note]], [[Note|alias]] and [[Note#Heading]].
+//! Wikilinks are resolved against the files loaded into the site and rendered as
+//! regular anchor tags; every wikilink is also collected as a `Link` so backlinks keep working.
+//! Embeds (![[Note]], ![[image.png]]) are handled here too, see the `embeds` module.
+
+use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use pulldown_cmark::{CowStr, Event, Tag};
+use slugify::slugify;
+
+use super::embeds;
+use crate::{
+    link::{EskerLinkType, Link},
+    md_file::MdFile,
+    site::Site,
+};
+
+/// The pieces of a wikilink, ex: [[target#heading|alias]]
+#[derive(Debug, PartialEq, Eq)]
+pub struct Wikilink {
+    pub target: String,
+    pub heading: Option<String>,
+    pub alias: Option<String>,
+}
+
+impl Wikilink {
+    /// parses the inside of a wikilink (everything between the `[[` and `]]`).
+    pub fn parse(inner: &str) -> Wikilink {
+        let (target_and_heading, alias) = match inner.split_once('|') {
+            Some((lhs, rhs)) => (lhs, Some(rhs.trim().to_string())),
+            None => (inner, None),
+        };
+
+        let (target, heading) = match target_and_heading.split_once('#') {
+            Some((lhs, rhs)) => (lhs, Some(rhs.trim().to_string())),
+            None => (target_and_heading, None),
+        };
+
+        Wikilink {
+            target: target.trim().to_string(),
+            heading: heading.filter(|h| !h.is_empty()),
+            alias: alias.filter(|a| !a.is_empty()),
+        }
+    }
+
+    /// The text to display for the link; mimics obsidian: "Note > Heading" unless there is an alias.
+    pub fn text(&self) -> String {
+        if let Some(alias) = &self.alias {
+            return alias.clone();
+        }
+
+        match &self.heading {
+            Some(heading) if self.target.is_empty() => heading.clone(),
+            Some(heading) => format!("{} > {}", self.target, heading),
+            None => self.target.clone(),
+        }
+    }
+}
+
+/// The outcome of looking up a link target in the vault.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Resolution<T> {
+    /// the full url of the page the target points to.
+    Found(T),
+    /// more than one file matches; holds the vault path of each candidate.
+    Ambiguous(Vec<T>),
+    NotFound,
+}
+
+/// A file in the vault index, with what's needed to link to (or embed) it.
+#[derive(Debug)]
+pub struct IndexedFile {
+    /// lowercased vault path without the extension, for matching: folder/note
+    key: String,
+    /// vault path as the user wrote it: folder/Note.md
+    pub vault_path: String,
+    /// the directory of the file in the vault: folder
+    pub dir: PathBuf,
+    pub full_url: String,
+    pub title: String,
+    /// the markdown of the file, without frontmatter; for embedding it elsewhere.
+    pub raw: String,
+}
+
+/// An index of every loaded file in the vault, used for resolving links
+/// written as absolute paths ([[folder/Note]]), relative paths ([[../Note]])
+/// or obsidian's "shortest path when possible" ([[Note]]).
+#[derive(Debug, Default)]
+pub struct VaultIndex {
+    entries: Vec<IndexedFile>,
+    /// key -> position in entries
+    paths: HashMap<String, usize>,
+    /// lowercased file stem -> positions in entries
+    stems: HashMap<String, Vec<usize>>,
+}
+
+impl VaultIndex {
+    pub fn new() -> VaultIndex {
+        VaultIndex::default()
+    }
+
+    pub fn insert(&mut self, md_file: &MdFile) {
+        self.insert_file(IndexedFile {
+            key: String::new(),
+            vault_path: format!("{}.md", md_file.vault_path().display()),
+            dir: md_file.web_path_parents.clone(),
+            full_url: md_file.full_url.clone(),
+            title: md_file.frontmatter.title.clone(),
+            raw: md_file.raw.clone(),
+        });
+    }
+
+    fn insert_file(&mut self, mut file: IndexedFile) {
+        file.key = Self::normalize(&file.vault_path);
+        let key = file.key.clone();
+        let stem = key.rsplit('/').next().unwrap_or(&key).to_string();
+        let position = self.entries.len();
+
+        self.entries.push(file);
+        self.paths.insert(key, position);
+        self.stems.entry(stem).or_default().push(position);
+    }
+
+    /// finds the file a link target points to; `from_dir` is the vault directory of
+    /// the file the link was written in, used for relative links.
+    pub fn resolve(&self, target: &str, from_dir: &Path) -> Resolution<&IndexedFile> {
+        let target = Self::normalize(target);
+        if target.is_empty() {
+            return Resolution::NotFound;
+        }
+
+        // explicitly relative: ./Note or ../Note
+        if target.starts_with("./") || target.starts_with("../") {
+            let joined = Self::normalize(&from_dir.join(&target).display().to_string());
+            return match Self::collapse_dots(&joined).and_then(|p| self.paths.get(&p)) {
+                Some(&i) => Resolution::Found(&self.entries[i]),
+                None => Resolution::NotFound,
+            };
+        }
+
+        // absolute path in vault, then relative to the current file.
+        if let Some(&i) = self.paths.get(&target) {
+            return Resolution::Found(&self.entries[i]);
+        }
+        let relative = Self::normalize(&from_dir.join(&target).display().to_string());
+        if let Some(&i) = self.paths.get(&relative) {
+            return Resolution::Found(&self.entries[i]);
+        }
+
+        // shortest path: any file whose path ends with the target.
+        let stem = target.rsplit('/').next().unwrap_or(&target);
+        let suffix = format!("/{}", target);
+        let candidates: Vec<&IndexedFile> = self
+            .stems
+            .get(stem)
+            .map(|positions| {
+                positions
+                    .iter()
+                    .map(|&i| &self.entries[i])
+                    .filter(|entry| entry.key.ends_with(&suffix))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        match candidates.len() {
+            0 => Resolution::NotFound,
+            1 => Resolution::Found(candidates[0]),
+            _ => {
+                let mut candidates = candidates;
+                candidates.sort_by(|a, b| a.vault_path.cmp(&b.vault_path));
+                Resolution::Ambiguous(candidates)
+            }
+        }
+    }
+
+    /// removes a file (by its vault path: folder/Note.md) from the index.
+    pub fn remove(&mut self, vault_path: &str) {
+        let key = Self::normalize(vault_path);
+        let entries = std::mem::take(&mut self.entries);
+        self.clear();
+        for file in entries.into_iter().filter(|file| file.key != key) {
+            self.insert_file(file);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.paths.clear();
+        self.stems.clear();
+    }
+
+    // obsidian matches link targets case insensitively and doesn't care about a leading slash or the extension.
+    pub fn normalize(target: &str) -> String {
+        let target = target.trim().trim_start_matches('/');
+        let target = target.strip_suffix(".md").unwrap_or(target);
+        target.to_lowercase()
+    }
+
+    // turns "posts/./drafts/../note" into "posts/note"; None if it climbs out of the vault.
+    fn collapse_dots(path: &str) -> Option<String> {
+        let mut parts: Vec<&str> = Vec::new();
+        for part in path.split('/') {
+            match part {
+                "" | "." => (),
+                ".." => {
+                    parts.pop()?;
+                }
+                part => parts.push(part),
+            }
+        }
+        Some(parts.join("/"))
+    }
+}
+
+/// Something wrong with a wikilink, found at `offset` (in bytes) into the markdown being parsed.
+#[derive(Debug, PartialEq, Eq)]
+pub enum WikilinkProblem {
+    NotFound { offset: usize, target: String },
+    /// an embedded image (or other attachment) that isn't in the vault.
+    MissingAttachment { offset: usize, target: String },
+    Ambiguous { offset: usize, target: String, candidates: Vec<String> },
+    /// a note that ends up embedding itself; `chain` is the vault paths of the embeds, in order.
+    RecursiveEmbed { offset: usize, chain: Vec<String> },
+}
+
+/// Everything collected from the wikilinks in a file, to be added to the site once parsing is done.
+#[derive(Default)]
+pub struct FoundWikilinks {
+    /// with the offset (in bytes) they were found at.
+    pub links: Vec<(Link, usize)>,
+    pub attachments: Vec<Link>,
+    pub problems: Vec<WikilinkProblem>,
+    /// every target linked to or embedded, including from within embedded notes.
+    pub targets: Vec<String>,
+    /// vault paths of the embedded notes, including the ones embedded in those.
+    pub embeds: Vec<String>,
+}
+
+/// stands in for an escaped `\[` while text is joined, so `\[[not a link]]` isn't taken for a wikilink.
+/// pulldown-cmark never hands out a NUL in text (it is replaced), so it can't clash with the markdown.
+const ESCAPED_BRACKET: char = '\0';
+
+pub struct ParseWikilinks<'a, 'b, I: Iterator<Item = (Event<'a>, Range<usize>)>> {
+    parent: I,
+    /// the markdown being parsed, for spotting escaped brackets.
+    source: &'a str,
+    site: &'b Site,
+    file_url: String,
+    file_title: String,
+    /// the vault directory of the file being parsed, for relative links.
+    file_dir: PathBuf,
+    /// the notes being embedded into one another (outermost first), to catch recursive embeds.
+    embed_stack: Vec<String>,
+    in_code_block: bool,
+    paragraph: Paragraph,
+    queue: VecDeque<(Event<'a>, Range<usize>)>,
+    found: FoundWikilinks,
+}
+
+/// Where we are in a paragraph. An embedded note is a block (a div), so it can't sit in a
+/// paragraph: the paragraph is closed before it and only reopened if more text follows.
+enum Paragraph {
+    Outside,
+    /// a paragraph started (at this range) but nothing has been written in it yet.
+    Pending(Range<usize>),
+    Open,
+}
+
+/// What an embed renders to.
+enum Embed {
+    /// the content of an embedded note.
+    Block(String),
+    /// an image, or a link when the note can't be transcluded.
+    Inline(String),
+}
+
+impl<'a, 'b, I: Iterator<Item = (Event<'a>, Range<usize>)>> ParseWikilinks<'a, 'b, I> {
+    pub fn new(parent: I, source: &'a str, site: &'b Site, md_file: &MdFile) -> Self {
+        Self {
+            parent,
+            source,
+            site,
+            file_url: md_file.full_url.clone(),
+            file_title: md_file.frontmatter.title.clone(),
+            file_dir: md_file.web_path_parents.clone(),
+            embed_stack: vec![format!("{}.md", md_file.vault_path().display())],
+            in_code_block: false,
+            paragraph: Paragraph::Outside,
+            queue: VecDeque::new(),
+            found: FoundWikilinks::default(),
+        }
+    }
+
+    /// for parsing the markdown of a note that is embedded in another one.
+    pub fn for_embed(
+        parent: I,
+        source: &'a str,
+        site: &'b Site,
+        file: &IndexedFile,
+        embed_stack: Vec<String>,
+    ) -> Self {
+        Self {
+            parent,
+            source,
+            site,
+            file_url: file.full_url.clone(),
+            file_title: file.title.clone(),
+            file_dir: file.dir.clone(),
+            embed_stack,
+            in_code_block: false,
+            paragraph: Paragraph::Outside,
+            queue: VecDeque::new(),
+            found: FoundWikilinks::default(),
+        }
+    }
+
+    pub fn finish(self) -> FoundWikilinks {
+        self.found
+    }
+
+    /// splits a chunk of text into text and html events, replacing every [[wikilink]] with
+    /// an anchor tag and every ![[embed]] with the content it embeds.
+    fn push_text(&mut self, text: String, range: Range<usize>) {
+        let mut rest = text.as_str();
+
+        while let Some(start) = rest.find("[[") {
+            let Some(len) = rest[start + 2..].find("]]") else {
+                break;
+            };
+            let inner = &rest[start + 2..start + 2 + len];
+            let is_embed = rest[..start].ends_with('!');
+            let text_before = if is_embed { &rest[..start - 1] } else { &rest[..start] };
+
+            if inner.is_empty() {
+                let text = unescape(&rest[..start + 2 + len + 2]);
+                self.push(Event::Text(text.into()), range.clone());
+            } else {
+                if !text_before.is_empty() {
+                    let text = unescape(text_before);
+                    self.push(Event::Text(text.into()), range.clone());
+                }
+                let wikilink = Wikilink::parse(inner);
+                if !is_embed {
+                    let html = self.render_wikilink(&wikilink, range.start);
+                    self.push(Event::Html(html.into()), range.clone());
+                } else {
+                    match self.render_embed(&wikilink, range.start) {
+                        Embed::Block(html) => self.push_block(html, range.clone()),
+                        Embed::Inline(html) => self.push(Event::Html(html.into()), range.clone()),
+                    }
+                }
+            }
+            rest = &rest[start + 2 + len + 2..];
+        }
+
+        if !rest.is_empty() {
+            self.push(Event::Text(unescape(rest).into()), range);
+        }
+    }
+
+    /// queues the next event from the parser, or the next run of text; the start of a
+    /// paragraph is held back, so this can leave the queue empty.
+    /// pulldown-cmark hands us `[[Note]]` as several text events ("[", "[Note", "]", "]"),
+    /// so consecutive text is joined before looking for wikilinks.
+    fn fill_queue(&mut self) -> Option<()> {
+        let (mut text, mut range) = match self.parent.next()? {
+            (Event::Text(text), range) if !self.in_code_block => (escaped_text(self.source, &text, &range), range),
+            (other, range) => {
+                self.push(other, range);
+                return Some(());
+            }
+        };
+
+        let mut next_item = None;
+        for (event, next_range) in self.parent.by_ref() {
+            match event {
+                Event::Text(more_text) => {
+                    text.push_str(&escaped_text(self.source, &more_text, &next_range));
+                    range.end = next_range.end;
+                }
+                other => {
+                    next_item = Some((other, next_range));
+                    break;
+                }
+            }
+        }
+
+        let text = embeds::strip_block_id(&text).to_string();
+        if text.contains("[[") {
+            self.push_text(text, range);
+        } else {
+            self.push(Event::Text(CowStr::from(unescape(&text))), range);
+        }
+
+        if let Some((event, next_range)) = next_item {
+            self.push(event, next_range);
+        }
+        Some(())
+    }
+
+    /// queues an event, holding back the start of a paragraph until there is something in it.
+    fn push(&mut self, event: Event<'a>, range: Range<usize>) {
+        self.track_code_block(&event);
+        match (&event, &self.paragraph) {
+            (Event::Start(Tag::Paragraph), _) => {
+                self.paragraph = Paragraph::Pending(range);
+                return;
+            }
+            (Event::End(Tag::Paragraph), Paragraph::Pending(_)) => {
+                self.paragraph = Paragraph::Outside;
+                return;
+            }
+            (Event::End(Tag::Paragraph), _) => self.paragraph = Paragraph::Outside,
+            (_, Paragraph::Pending(start)) => {
+                self.queue.push_back((Event::Start(Tag::Paragraph), start.clone()));
+                self.paragraph = Paragraph::Open;
+            }
+            _ => (),
+        }
+        self.queue.push_back((event, range));
+    }
+
+    /// queues block level html, closing the paragraph it was found in first.
+    fn push_block(&mut self, html: String, range: Range<usize>) {
+        if let Paragraph::Open = self.paragraph {
+            self.queue.push_back((Event::End(Tag::Paragraph), range.clone()));
+            self.paragraph = Paragraph::Pending(range.clone());
+        }
+        self.queue.push_back((Event::Html(html.into()), range));
+    }
+
+    /// looks up the note a wikilink points to, noting any problems doing so (a missing note only
+    /// with `report_missing`). Returns the url to link to, and the note when it was found.
+    fn resolve(
+        &mut self,
+        wikilink: &Wikilink,
+        offset: usize,
+        report_missing: bool,
+    ) -> (String, Option<&'b IndexedFile>) {
+        if wikilink.target.is_empty() {
+            return (self.with_heading(self.file_url.clone(), wikilink), None);
+        }
+
+        self.found.targets.push(wikilink.target.clone());
+        let site: &'b Site = self.site;
+        let file = match site.vault_index.resolve(&wikilink.target, &self.file_dir) {
+            Resolution::Found(file) => Some(file),
+            Resolution::Ambiguous(candidates) => {
+                self.found.problems.push(WikilinkProblem::Ambiguous {
+                    offset,
+                    target: wikilink.target.clone(),
+                    candidates: candidates.iter().map(|c| c.vault_path.clone()).collect(),
+                });
+                None
+            }
+            Resolution::NotFound => {
+                if report_missing {
+                    self.found.problems.push(WikilinkProblem::NotFound {
+                        offset,
+                        target: wikilink.target.clone(),
+                    });
+                }
+                None
+            }
+        };
+
+        // unknown files are linked the same way a markdown link to them would be.
+        let url = match file {
+            Some(file) => file.full_url.clone(),
+            None => Link::build_internal_url(site, &wikilink.target),
+        };
+
+        (self.with_heading(url, wikilink), file)
+    }
+
+    fn with_heading(&self, url: String, wikilink: &Wikilink) -> String {
+        match &wikilink.heading {
+            Some(block_id) if block_id.starts_with('^') => format!("{}#{}", url, block_id),
+            Some(heading) => format!("{}#{}", url, slugify!(heading)),
+            None => url,
+        }
+    }
+
+    fn render_wikilink(&mut self, wikilink: &Wikilink, offset: usize) -> String {
+        // a missing note is reported by `Site::check_internal_links`, as a broken link.
+        let (url, _file) = self.resolve(wikilink, offset, false);
+        let text = wikilink.text();
+
+        let mut link = Link::empty();
+        link.url = url.clone();
+        link.is_internal = true;
+        link.title = text.clone();
+        link.originating_file_url = Some(self.file_url.clone());
+        link.originating_file_title = Some(self.file_title.clone());
+        link.original = Some(wikilink.target.clone());
+        link.link_type = EskerLinkType::Default;
+        self.found.links.push((link, offset));
+
+        anchor(&url, &text)
+    }
+
+    /// transcludes an embedded note (or part of it), or renders an embedded attachment.
+    fn render_embed(&mut self, wikilink: &Wikilink, offset: usize) -> Embed {
+        if !embeds::is_note(&wikilink.target) {
+            return Embed::Inline(self.render_attachment_embed(wikilink, offset));
+        }
+
+        let (url, file) = self.resolve(wikilink, offset, true);
+        let Some(file) = file else {
+            return Embed::Inline(anchor(&url, &wikilink.text()));
+        };
+        self.found.embeds.push(file.vault_path.clone());
+
+        if self.embed_stack.contains(&file.vault_path) {
+            let mut chain = self.embed_stack.clone();
+            chain.push(file.vault_path.clone());
+            self.found.problems.push(WikilinkProblem::RecursiveEmbed { offset, chain });
+            return Embed::Inline(anchor(&url, &wikilink.text()));
+        }
+
+        let Some(markdown) = embeds::extract(&file.raw, wikilink.heading.as_deref()) else {
+            self.found.problems.push(WikilinkProblem::NotFound {
+                offset,
+                target: format!("{}#{}", wikilink.target, wikilink.heading.clone().unwrap_or_default()),
+            });
+            return Embed::Inline(anchor(&url, &wikilink.text()));
+        };
+
+        let mut embed_stack = self.embed_stack.clone();
+        embed_stack.push(file.vault_path.clone());
+        let (html, found) = super::render_embed(&markdown, self.site, file, embed_stack);
+
+        // problems with links inside the embedded note are reported for the note itself,
+        // recursion however only shows up here. The same cycle can be met more than once
+        // in the embedded note (it embeds this one twice): it is still the one embed here.
+        for problem in found.problems {
+            if let WikilinkProblem::RecursiveEmbed { chain, .. } = problem {
+                let problem = WikilinkProblem::RecursiveEmbed { offset, chain };
+                if !self.found.problems.contains(&problem) {
+                    self.found.problems.push(problem);
+                }
+            }
+        }
+        self.found.attachments.extend(found.attachments);
+        self.found.targets.extend(found.targets);
+        self.found.embeds.extend(found.embeds);
+
+        Embed::Block(format!(
+            r#"<div class="embed" data-embed-src="{}">{}</div>"#,
+            html_escape::encode_double_quoted_attribute(&url),
+            html
+        ))
+    }
+
+    fn render_attachment_embed(&mut self, wikilink: &Wikilink, offset: usize) -> String {
+        let Some(vault_path) = embeds::find_attachment(self.site, &wikilink.target, &self.file_dir) else {
+            self.found.problems.push(WikilinkProblem::MissingAttachment {
+                offset,
+                target: wikilink.target.clone(),
+            });
+            return html_escape::encode_text(&format!("![[{}]]", wikilink.target)).to_string();
+        };
+
+        let encoded_path = url_escape::encode_fragment(&vault_path).to_string();
+        let url = self.site.build_with_baseurl(encoded_path.clone());
+        let dimensions = wikilink.alias.as_deref().and_then(embeds::parse_dimensions);
+        let text = match (&wikilink.alias, dimensions) {
+            (Some(alias), None) => alias.clone(),
+            _ => wikilink.target.clone(),
+        };
+        self.found
+            .attachments
+            .push(Link::new_attachment_link(url.clone(), text.clone(), encoded_path));
+
+        if !embeds::is_image(&vault_path) {
+            return anchor(&url, &text);
+        }
+
+        let mut img = format!(
+            r#"<img src="{}" alt="{}""#,
+            html_escape::encode_double_quoted_attribute(&url),
+            html_escape::encode_double_quoted_attribute(&text)
+        );
+        if let Some((width, height)) = dimensions {
+            img.push_str(&format!(r#" width="{}""#, width));
+            if let Some(height) = height {
+                img.push_str(&format!(r#" height="{}""#, height));
+            }
+        }
+        img.push_str(" />");
+        img
+    }
+
+    fn track_code_block(&mut self, event: &Event) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => self.in_code_block = true,
+            Event::End(Tag::CodeBlock(_)) => self.in_code_block = false,
+            _ => (),
+        }
+    }
+}
+
+/// the text of a text event, with an escaped `\[` swapped for `ESCAPED_BRACKET`.
+/// pulldown-cmark drops the backslash, so it is looked for in the markdown itself.
+fn escaped_text(source: &str, text: &str, range: &Range<usize>) -> String {
+    let backslashes = source[..range.start].bytes().rev().take_while(|b| *b == b'\\').count();
+    if text == "[" && backslashes % 2 == 1 {
+        ESCAPED_BRACKET.to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+fn unescape(text: &str) -> String {
+    text.replace(ESCAPED_BRACKET, "[")
+}
+
+fn anchor(url: &str, text: &str) -> String {
+    format!(
+        r#"<a href="{}">{}</a>"#,
+        html_escape::encode_double_quoted_attribute(url),
+        html_escape::encode_text(text)
+    )
+}
+
+impl<'a, 'b, I: Iterator<Item = (Event<'a>, Range<usize>)>> Iterator for ParseWikilinks<'a, 'b, I> {
+    type Item = (Event<'a>, Range<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.queue.is_empty() {
+            self.fill_queue()?;
+        }
+        self.queue.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IndexedFile, Resolution, VaultIndex, Wikilink};
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_wikilink() {
+        let res = Wikilink::parse("folder/Note#Some Heading|my alias");
+        assert_eq!(res.target, "folder/Note");
+        assert_eq!(res.heading, Some("Some Heading".to_string()));
+        assert_eq!(res.alias, Some("my alias".to_string()));
+        assert_eq!(res.text(), "my alias");
+    }
+
+    #[test]
+    fn test_wikilink_text() {
+        assert_eq!(Wikilink::parse("Note").text(), "Note");
+        assert_eq!(Wikilink::parse("Note#Heading").text(), "Note > Heading");
+        assert_eq!(Wikilink::parse("#Heading").text(), "Heading");
+    }
+
+    #[test]
+    fn test_collapse_dots() {
+        assert_eq!(VaultIndex::collapse_dots("posts/./drafts/../note"), Some("posts/note".to_string()));
+        assert_eq!(VaultIndex::collapse_dots("../note"), None);
+    }
+
+    fn insert(index: &mut VaultIndex, vault_path: &str, full_url: &str) {
+        let dir = Path::new(vault_path).parent().unwrap().to_path_buf();
+        index.insert_file(IndexedFile {
+            key: String::new(),
+            vault_path: format!("{}.md", vault_path),
+            dir,
+            full_url: full_url.to_string(),
+            title: String::new(),
+            raw: String::new(),
+        });
+    }
+
+    fn found_url(resolution: Resolution<&IndexedFile>) -> Option<&str> {
+        match resolution {
+            Resolution::Found(file) => Some(&file.full_url),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut index = VaultIndex::new();
+        insert(&mut index, "work/2024/Meeting", "/work/2024/meeting.html");
+        insert(&mut index, "notes/Idea", "/notes/idea.html");
+        insert(&mut index, "Idea", "/idea.html");
+        insert(&mut index, "archive/Idea", "/archive/idea.html");
+
+        assert_eq!(found_url(index.resolve("Meeting", Path::new(""))), Some("/work/2024/meeting.html"));
+        assert_eq!(found_url(index.resolve("2024/meeting.md", Path::new(""))), Some("/work/2024/meeting.html"));
+        assert_eq!(found_url(index.resolve("Idea", Path::new(""))), Some("/idea.html"));
+        assert_eq!(found_url(index.resolve("Idea", Path::new("notes"))), Some("/idea.html"));
+        assert_eq!(found_url(index.resolve("./Idea", Path::new("notes"))), Some("/notes/idea.html"));
+        assert_eq!(found_url(index.resolve("../Idea", Path::new("notes"))), Some("/idea.html"));
+        assert_eq!(found_url(index.resolve("Missing", Path::new(""))), None);
+    }
+
+    #[test]
+    fn test_resolve_ambiguous() {
+        let mut index = VaultIndex::new();
+        insert(&mut index, "notes/Idea", "/notes/idea.html");
+        insert(&mut index, "archive/Idea", "/archive/idea.html");
+
+        match index.resolve("Idea", Path::new("")) {
+            Resolution::Ambiguous(candidates) => {
+                let paths: Vec<&str> = candidates.iter().map(|c| c.vault_path.as_str()).collect();
+                assert_eq!(paths, vec!["archive/Idea.md", "notes/Idea.md"]);
+            }
+            _ => panic!("expected an ambiguous resolution"),
+        }
+        assert_eq!(found_url(index.resolve("Idea", Path::new("notes"))), Some("/notes/idea.html"));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut index = VaultIndex::new();
+        insert(&mut index, "notes/Idea", "/notes/idea.html");
+        insert(&mut index, "archive/Idea", "/archive/idea.html");
+
+        index.remove("archive/Idea.md");
+        assert_eq!(found_url(index.resolve("Idea", Path::new(""))), Some("/notes/idea.html"));
+        assert_eq!(found_url(index.resolve("archive/Idea", Path::new(""))), None);
+    }
+}
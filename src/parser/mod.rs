@@ -1,21 +1,75 @@
+pub mod callouts;
+pub mod embeds;
 pub mod headlines;
 pub mod links;
 pub mod syntax_highlight;
+pub mod tags;
 
-use crate::{link::{Link, EskerLinkType}, md_file::MdFile, site::Site};
+use crate::{errors::Errors, link::{Link, EskerLinkType}, md_file::MdFile, site::Site};
 use pulldown_cmark::{html, Event, Parser, Tag, Options};
 use slugify::slugify;
 use syntax_highlight::CodeBlockSyntaxHighlight;
 
+use self::callouts::ParseCallouts;
 use self::headlines::ParseHeadlines;
+use self::tags::ParseInlineTags;
+use self::links::{FoundWikilinks, IndexedFile, ParseWikilinks, WikilinkProblem};
+use std::ops::Range;
 
-pub fn new(md_file: &mut MdFile, site: &mut Site) -> String {
-    // TODO: how can I not clone this here?
-    let raw = md_file.raw.clone();
+pub(crate) fn options() -> Options {
     let mut options = Options::empty();
     options.insert(Options::ENABLE_STRIKETHROUGH);
     options.insert(Options::ENABLE_FOOTNOTES);
-    let mut parser = Parser::new_ext(&raw, options);
+    options
+}
+
+/// The html of a parsed file and everything found in it, to be added to the site
+/// once every file is parsed (files are parsed in parallel, see `Site::load_files`).
+#[derive(Debug, Default)]
+pub struct ParsedMarkdown {
+    pub html: String,
+    pub links: Vec<Link>,
+    pub attachments: Vec<Link>,
+    pub errors: Errors,
+}
+
+pub fn new(md_file: &mut MdFile, site: &Site) -> ParsedMarkdown {
+    let mut parsed = ParsedMarkdown::default();
+    // TODO: how can I not clone this here?
+    let raw = md_file.raw.clone();
+    let parser = Parser::new_ext(&raw, options()).into_offset_iter();
+
+    // wikilinks are resolved first, as they need to look up other files in the site.
+    let mut wikilinks = ParseWikilinks::new(parser, &raw, site, md_file);
+    let events: Vec<(Event, Range<usize>)> = wikilinks.by_ref().collect();
+    let found = wikilinks.finish();
+    for (mut link, offset) in found.links {
+        link.line = Some(md_file.line_number(offset));
+        md_file.dependencies.links_to.insert(link.url_without_fragment().to_string());
+        parsed.links.push(link);
+    }
+    for target in &found.targets {
+        md_file.dependencies.add_link_target(target);
+    }
+    md_file.dependencies.embeds.extend(found.embeds);
+    parsed.attachments.extend(found.attachments);
+    for problem in found.problems {
+        let filepath = md_file.frontmatter.get_filepath_as_str();
+        match problem {
+            WikilinkProblem::NotFound { offset, target } => {
+                parsed.errors.add_unresolved_link(filepath, md_file.line_number(offset), &target)
+            }
+            WikilinkProblem::MissingAttachment { offset, target } => {
+                parsed.errors.add_missing_attachment(filepath, md_file.line_number(offset), &target)
+            }
+            WikilinkProblem::Ambiguous { offset, target, candidates } => {
+                parsed.errors.add_ambiguous_link(filepath, md_file.line_number(offset), &target, &candidates)
+            }
+            WikilinkProblem::RecursiveEmbed { offset, chain } => {
+                parsed.errors.add_recursive_embed(filepath, md_file.line_number(offset), &chain)
+            }
+        }
+    }
 
     // -- parser stuff
 
@@ -25,21 +79,43 @@ pub fn new(md_file: &mut MdFile, site: &mut Site) -> String {
     let mut link = Link::empty();
     let mut toc_link_placeholder = Link::empty();
 
-    let parser = parser.map(|event| -> Event {
+    let parser = events.into_iter().map(|(event, range)| -> Event {
         match event {
             Event::Start(tag) => match tag {
-                Tag::Link(_link_type, ref _url, ref _title) => {
-                    link.fill_from_parser(
+                Tag::Link(_link_type, ref url, ref _title) => {
+                    let target = url.to_string();
+                    if Link::is_internal(&target) && !Link::is_mailto(&target) {
+                        md_file.dependencies.add_link_target(&target);
+                    }
+                    let ambiguous_candidates = link.fill_from_parser(
                         tag,
                         site,
+                        &md_file.web_path_parents,
                         Some(md_file.full_url.clone()),
                         Some(md_file.frontmatter.title.clone()),
+                        &mut parsed.attachments,
                     );
+                    link.line = Some(md_file.line_number(range.start));
+                    if let Some(candidates) = ambiguous_candidates {
+                        parsed.errors.add_ambiguous_link(
+                            md_file.frontmatter.get_filepath_as_str(),
+                            md_file.line_number(range.start),
+                            &target,
+                            &candidates,
+                        );
+                    }
                     capturing = true;
                     Event::Start(link.for_parser(site))
                 }
                 Tag::Image(link_type, url, title) => {
-                    Event::Start(Link::update_img_link(link_type, url, title, site))
+                    if Link::is_internal(&url) && !attachment_exists(site, &url) {
+                        parsed.errors.add_missing_attachment(
+                            md_file.frontmatter.get_filepath_as_str(),
+                            md_file.line_number(range.start),
+                            &url,
+                        );
+                    }
+                    Event::Start(Link::update_img_link(link_type, url, title, site, &mut parsed.attachments))
                 }
 
                 Tag::Heading(heading_level, fragment_id, css_classes) => {
@@ -69,7 +145,10 @@ pub fn new(md_file: &mut MdFile, site: &mut Site) -> String {
 
             Event::End(tag) => match tag {
                 Tag::Link(link_type, url, title) => {
-                    site.add_link(link.clone());
+                    if link.is_internal {
+                        md_file.dependencies.links_to.insert(link.url_without_fragment().to_string());
+                    }
+                    parsed.links.push(link.clone());
                     Event::End(Tag::Link(link_type, url, title))
                 }
 
@@ -88,10 +167,57 @@ pub fn new(md_file: &mut MdFile, site: &mut Site) -> String {
     });
 
     // transformation section
+    let parser = ParseInlineTags::new(parser, Some(&site.tag_pages));
+    let parser = ParseCallouts::new(parser);
     let parser = CodeBlockSyntaxHighlight::new(parser);
     let parser = ParseHeadlines::new(parser);
 
     let mut html_output = String::new();
     html::push_html(&mut html_output, parser);
-    html_output
+    parsed.html = html_output;
+    parsed
+}
+
+/// images are linked to by their path in the vault: ![](attachments/cat%20photo.png)
+fn attachment_exists(site: &Site, url: &str) -> bool {
+    if url.starts_with("data:") {
+        return true;
+    }
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    site.dir.join(url_escape::decode(path.trim_start_matches('/')).as_ref()).is_file()
+}
+
+/// renders markdown pulled out of an embedded note. Links in it belong to the embedded note
+/// (and were collected when that note was parsed) so they are only rewritten here, not collected.
+pub fn render_embed(
+    markdown: &str,
+    site: &Site,
+    file: &IndexedFile,
+    embed_stack: Vec<String>,
+) -> (String, FoundWikilinks) {
+    let parser = Parser::new_ext(markdown, options()).into_offset_iter();
+    let mut wikilinks = ParseWikilinks::for_embed(parser, markdown, site, file, embed_stack);
+    let events: Vec<Event> = wikilinks.by_ref().map(|(event, _range)| event).collect();
+    let found = wikilinks.finish();
+
+    let parser = events.into_iter().map(|event| match event {
+        Event::Start(Tag::Link(link_type, url, title)) => {
+            let mut link = Link::empty();
+            link.fill_from_parser(Tag::Link(link_type, url, title), site, &file.dir, None, None, &mut Vec::new());
+            Event::Start(link.for_parser(site))
+        }
+        Event::Start(Tag::Image(link_type, url, title)) if Link::is_internal(&url) => {
+            Event::Start(Tag::Image(link_type, site.build_with_baseurl(url.to_string()).into(), title))
+        }
+        _ => event,
+    });
+
+    let parser = ParseInlineTags::new(parser, Some(&site.tag_pages));
+    let parser = ParseCallouts::new(parser);
+    let parser = CodeBlockSyntaxHighlight::new(parser);
+    let parser = ParseHeadlines::without_ids(parser);
+
+    let mut html_output = String::new();
+    html::push_html(&mut html_output, parser);
+    (html_output, found)
 }
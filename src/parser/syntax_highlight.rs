@@ -16,7 +16,7 @@ lazy_static! {
 }
 
 lazy_static! {
-    static ref BLOCK_CODE_SPEC: Regex = Regex::new(r"^\w+$").unwrap();
+    static ref BLOCK_CODE_SPEC: Regex = Regex::new(r"^[\w+#-]+$").unwrap();
 }
 
 lazy_static! {
@@ -49,15 +49,15 @@ fn syntect_lang_name(lang: &str) -> &str {
     }
 }
 
+// the language of a fenced code block is the first word of its info string:
+// "rust,ignore", "rust title=main.rs" and "{.rust}" are all rust. Anything
+// else is shown as plain code.
 fn parse_code_spec(lang: &str) -> Option<String> {
-    if lang.is_empty() {
-        return None;
-    }
-    if BLOCK_CODE_SPEC.is_match(lang) {
-        Some(lang.to_string())
-    } else {
-        panic!("couldn't match code spec: `{}`", lang)
-    }
+    let lang = lang
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .next()?
+        .trim_matches(|c| c == '{' || c == '}' || c == '.');
+    BLOCK_CODE_SPEC.is_match(lang).then(|| lang.to_string())
 }
 
 
@@ -98,21 +98,17 @@ impl<'a, I: Iterator<Item = Event<'a>>> Iterator for CodeBlockSyntaxHighlight<'a
     }
 }
 
+// code that can't be highlighted is shown as is.
 fn push_code_highlight<S: AsRef<str>>(s: &mut String, lang: Option<S>, code: &str) {
-    if let Some(spec) = lang.and_then(|x| HighlightSpec::find(x.as_ref())) {
-        match highlight(&spec, code) {
-            Ok(highlight) => {
-                s.push_str(r#"<code class="highlight code "#);
-                s.push_str(&spec.html_id);
-                s.push_str(r#"">"#);
-                s.push_str(&highlight);
-                s.push_str("</code>");
-            }
-
-            Err(_err) => {
-                panic!();
-            }
-        }
+    let spec = lang.and_then(|x| HighlightSpec::find(x.as_ref()));
+    let highlighted = spec.and_then(|spec| Some((highlight(&spec, code).ok()?, spec)));
+
+    if let Some((highlight, spec)) = highlighted {
+        s.push_str(r#"<code class="highlight code "#);
+        s.push_str(&spec.html_id);
+        s.push_str(r#"">"#);
+        s.push_str(&highlight);
+        s.push_str("</code>");
     } else {
         s.push_str("<code>");
         s.push_str(&html_escape::encode_safe(&code));
@@ -133,11 +129,11 @@ fn highlight(spec: &HighlightSpec, code: &str) -> Result<String> {
 
     let generated = html_generator.finalize();
 
-    let cap = SPAN_WRAPPER
-        .captures(&generated)
-        .expect("Failed toi match syntax span");
-
-    Ok(cap[1].trim().to_string())
+    // (an empty code block doesn't get wrapped in a span)
+    match SPAN_WRAPPER.captures(&generated) {
+        Some(cap) => Ok(cap[1].trim().to_string()),
+        None => Ok(generated.trim().to_string()),
+    }
 }
 
 struct HighlightSpec<'a> {
@@ -174,3 +170,30 @@ pub fn dump_syntax_binary() {
     let ss = builder.build();
     dumps::dump_to_uncompressed_file(&ss, file).unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_code_spec() {
+        assert_eq!(parse_code_spec("rust"), Some("rust".to_string()));
+        assert_eq!(parse_code_spec("rust,ignore"), Some("rust".to_string()));
+        assert_eq!(parse_code_spec("rust title=main.rs"), Some("rust".to_string()));
+        assert_eq!(parse_code_spec("{.python}"), Some("python".to_string()));
+        assert_eq!(parse_code_spec("c++"), Some("c++".to_string()));
+        assert_eq!(parse_code_spec(""), None);
+        assert_eq!(parse_code_spec("<script>"), None);
+    }
+
+    #[test]
+    fn test_push_code_highlight() {
+        let mut html = String::new();
+        push_code_highlight(&mut html, Some("rust"), "");
+        assert_eq!(html, r#"<code class="highlight code rust"></code>"#);
+
+        let mut html = String::new();
+        push_code_highlight(&mut html, Some("not-a-language"), "<b>");
+        assert_eq!(html, "<code>&lt;b&gt;</code>");
+    }
+}
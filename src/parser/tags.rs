@@ -0,0 +1,344 @@
+//! Inline tags: `#tag` (or `#nested/tag`) anywhere in the text of a note, except in code,
+//! headings and links. They are rendered as links to their tag page.
+
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use lazy_static::lazy_static;
+use pulldown_cmark::{CowStr, Event, Parser, Tag};
+use regex::Regex;
+use slugify::slugify;
+
+use crate::site::Site;
+
+lazy_static! {
+    static ref INLINE_TAG: Regex = Regex::new(r"(^|\s)#([\p{L}\p{N}_/-]+)").unwrap();
+}
+
+/// the inline tags in a note, in the order they appear.
+pub fn find(markdown: &str) -> Vec<String> {
+    let parser = Parser::new_ext(markdown, super::options());
+    let mut tags = ParseInlineTags::new(parser, None);
+    tags.by_ref().for_each(drop);
+    tags.found
+}
+
+/// where tag pages are: <baseurl>/<tags_url>. None if the site doesn't have them.
+pub fn tag_pages_url(site: &Site) -> Option<String> {
+    let tags_url = site.config.tags_url.as_ref()?;
+    Some(site.build_with_baseurl(tags_url.clone()))
+}
+
+/// every tag, and every level above them: "a/b/c" -> "a", "a/b", "a/b/c".
+pub fn with_parents<'a>(tags: impl Iterator<Item = &'a String>) -> BTreeSet<String> {
+    let mut all_tags = BTreeSet::new();
+    for tag in tags {
+        let mut parts: Vec<&str> = Vec::new();
+        for part in tag.split('/').filter(|part| !part.is_empty()) {
+            parts.push(part);
+            all_tags.insert(parts.join("/"));
+        }
+    }
+    all_tags
+}
+
+/// The page of every tag (and every level of nested tags). Tags are slugged to be safe in
+/// urls and on any filesystem: "Area/Déjà Vu" is at "area/deja-vu.html". Tags that slug
+/// the same ("C++" and "c++") get a suffix in the order they sort in: "c.html", "c-2.html".
+/// With clean urls, a tag's page is the index.html of its directory: "c/index.html".
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TagPages {
+    /// <baseurl>/<tags_url>, None if the site doesn't have tag pages.
+    url: Option<String>,
+    clean_urls: bool,
+    /// tag -> path of its page in the tags directory, without ".html".
+    paths: HashMap<String, String>,
+}
+
+impl TagPages {
+    pub fn new<'a>(url: Option<String>, clean_urls: bool, tags: impl Iterator<Item = &'a String>) -> Self {
+        let mut paths: HashMap<String, String> = HashMap::new();
+        // compared in lowercase, for case insensitive filesystems. "index" is the tag index.
+        let mut taken: HashSet<String> = HashSet::from(["index".to_string()]);
+
+        // parents sort before their children, so their path is known first.
+        for tag in with_parents(tags) {
+            let (parent, name) = match tag.rsplit_once('/') {
+                Some((parent, name)) => (paths.get(parent).map(|path| format!("{}/", path)), name),
+                None => (None, tag.as_str()),
+            };
+            let path = format!("{}{}", parent.unwrap_or_default(), slug(name));
+            let mut unique = path.clone();
+            let mut suffix = 1;
+            while !taken.insert(unique.to_lowercase()) {
+                suffix += 1;
+                unique = format!("{}-{}", path, suffix);
+            }
+            paths.insert(tag, unique);
+        }
+        TagPages { url, clean_urls, paths }
+    }
+
+    /// where the tag's page is in the tags directory, without ".html".
+    pub fn path(&self, tag: &str) -> String {
+        match self.paths.get(tag) {
+            Some(path) => path.clone(),
+            // not a tag of the site (yet): its page would be here, unless it collides.
+            None => tag.split('/').filter(|part| !part.is_empty()).map(slug).collect::<Vec<_>>().join("/"),
+        }
+    }
+
+    /// where the tag's page is written in the tags directory: nested/tag.html
+    /// (nested/tag/index.html with clean urls).
+    pub fn html_path(&self, tag: &str) -> String {
+        match self.clean_urls {
+            true => format!("{}/index.html", self.path(tag)),
+            false => format!("{}.html", self.path(tag)),
+        }
+    }
+
+    /// the url of a tag's page: <tag_pages_url>/nested/tag.html (or nested/tag/)
+    pub fn url(&self, tag: &str) -> Option<String> {
+        let url = self.url.as_ref()?;
+        let html_path = crate::util::page_url_path(&self.html_path(tag), self.clean_urls);
+        Some(format!("{}/{}", url, url_escape::encode_path(&html_path)))
+    }
+
+    /// the url of a file in the tag's directory: <tag_pages_url>/nested/tag/atom.xml
+    pub fn file_url(&self, tag: &str, file_name: &str) -> Option<String> {
+        let url = self.url.as_ref()?;
+        let file_name = crate::util::page_url_path(file_name, self.clean_urls);
+        Some(format!("{}/{}/{}", url, url_escape::encode_path(&self.path(tag)), file_name))
+    }
+
+    /// the url of the page listing every tag.
+    pub fn index_url(&self) -> Option<String> {
+        let url = self.url.as_ref()?;
+        Some(format!("{}/{}", url, crate::util::page_url_path("index.html", self.clean_urls)))
+    }
+
+    /// every tag (and parent tag) with a page, and its path.
+    pub fn paths(&self) -> &HashMap<String, String> {
+        &self.paths
+    }
+}
+
+/// one part of a tag, made safe for a filename: "Déjà Vu" -> "deja-vu". Tags with nothing
+/// left after slugging ("++") are all "tag", and are told apart by their suffix.
+fn slug(part: &str) -> String {
+    match slugify!(part) {
+        slug if slug.is_empty() => "tag".to_string(),
+        slug => slug,
+    }
+}
+
+pub struct ParseInlineTags<'a, 'b, I: Iterator<Item = Event<'a>>> {
+    parent: I,
+    tag_pages: Option<&'b TagPages>,
+    queue: VecDeque<Event<'a>>,
+    in_code_block: bool,
+    in_heading: bool,
+    in_link: bool,
+    /// whether the text so far ends in whitespace (or a new block), which a tag must follow.
+    at_word_boundary: bool,
+    found: Vec<String>,
+}
+
+impl<'a, 'b, I: Iterator<Item = Event<'a>>> ParseInlineTags<'a, 'b, I> {
+    pub fn new(parent: I, tag_pages: Option<&'b TagPages>) -> Self {
+        Self {
+            parent,
+            tag_pages,
+            queue: VecDeque::new(),
+            in_code_block: false,
+            in_heading: false,
+            in_link: false,
+            at_word_boundary: true,
+            found: Vec::new(),
+        }
+    }
+
+    fn track(&mut self, event: &Event) {
+        self.at_word_boundary = match event {
+            Event::Start(Tag::CodeBlock(_)) => {
+                self.in_code_block = true;
+                true
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                self.in_code_block = false;
+                true
+            }
+            Event::Start(Tag::Heading(..)) => {
+                self.in_heading = true;
+                true
+            }
+            Event::End(Tag::Heading(..)) => {
+                self.in_heading = false;
+                true
+            }
+            Event::Start(Tag::Link(..)) => {
+                self.in_link = true;
+                self.at_word_boundary
+            }
+            Event::End(Tag::Link(..)) => {
+                self.in_link = false;
+                false
+            }
+            Event::Start(tag) if is_inline(tag) => self.at_word_boundary,
+            Event::End(tag) if is_inline(tag) => false,
+            Event::Start(_) | Event::End(_) => true,
+            Event::SoftBreak | Event::HardBreak | Event::Rule | Event::TaskListMarker(_) => true,
+            Event::Text(text) => text.ends_with(char::is_whitespace),
+            Event::Code(_) | Event::Html(_) | Event::FootnoteReference(_) => false,
+        }
+    }
+
+    /// queues up the text, with its tags as links.
+    fn push_text(&mut self, text: String) {
+        let mut rest = 0;
+        for captures in INLINE_TAG.captures_iter(&text) {
+            // at the start of the text, the tag has to follow whatever came before it.
+            if captures[1].is_empty() && !self.at_word_boundary {
+                continue;
+            }
+            let Some(tag) = tag_name(&captures[2]) else {
+                continue;
+            };
+            let hash = captures.get(2).map_or(0, |m| m.start() - 1);
+
+            if hash > rest {
+                self.queue.push_back(Event::Text(CowStr::from(text[rest..hash].to_string())));
+            }
+            self.queue.push_back(Event::Html(self.tag_html(tag).into()));
+            if !self.found.iter().any(|found| found == tag) {
+                self.found.push(tag.to_string());
+            }
+            rest = hash + 1 + tag.len();
+        }
+
+        if rest < text.len() {
+            self.queue.push_back(Event::Text(CowStr::from(text[rest..].to_string())));
+        }
+        self.at_word_boundary = text.ends_with(char::is_whitespace);
+    }
+
+    fn tag_html(&self, tag: &str) -> String {
+        let text = html_escape::encode_text(&format!("#{}", tag)).to_string();
+        match self.tag_pages.and_then(|tag_pages| tag_pages.url(tag)) {
+            Some(url) => {
+                format!(
+                    r#"<a class="tag" href="{}">{}</a>"#,
+                    html_escape::encode_double_quoted_attribute(&url),
+                    text
+                )
+            }
+            None => format!(r#"<span class="tag">{}</span>"#, text),
+        }
+    }
+}
+
+impl<'a, 'b, I: Iterator<Item = Event<'a>>> Iterator for ParseInlineTags<'a, 'b, I> {
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(event) = self.queue.pop_front() {
+            return Some(event);
+        }
+
+        let text = match self.parent.next()? {
+            Event::Text(text) if !(self.in_code_block || self.in_heading || self.in_link) => text,
+            event => {
+                self.track(&event);
+                return Some(event);
+            }
+        };
+
+        // text gets split up around characters that could be markup ("#my_tag" -> "#my", "_tag").
+        let mut joined = text.to_string();
+        let mut next_event = None;
+        for event in self.parent.by_ref() {
+            match event {
+                Event::Text(more) => joined.push_str(&more),
+                event => {
+                    next_event = Some(event);
+                    break;
+                }
+            }
+        }
+
+        self.push_text(joined);
+        if let Some(event) = next_event {
+            self.track(&event);
+            self.queue.push_back(event);
+        }
+        self.queue.pop_front()
+    }
+}
+
+fn is_inline(tag: &Tag) -> bool {
+    matches!(
+        tag,
+        Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..) | Tag::Image(..)
+    )
+}
+
+// "#2023" isn't a tag, "#y2023" is; "#nested/tag/" is "nested/tag".
+fn tag_name(tag: &str) -> Option<&str> {
+    let tag = tag.trim_end_matches('/');
+    let is_tag = !tag.starts_with('/') && tag.chars().any(|c| !c.is_numeric() && c != '/');
+    is_tag.then_some(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::html::push_html;
+
+    fn render(markdown: &str) -> String {
+        let parser = Parser::new_ext(markdown, crate::parser::options());
+        let mut html = String::new();
+        let tag_pages = TagPages::new(Some("/tags".to_string()), false, [].iter());
+        push_html(&mut html, ParseInlineTags::new(parser, Some(&tag_pages)));
+        html
+    }
+
+    #[test]
+    fn test_find() {
+        let markdown = "# Heading #not-a-tag\n\nsee #my_tag, #project/esker/ and #2023 \
+            but not `#code` or [#link](url) or a#b.\n\n```\n#code\n```\n\n- #in-a-list #my_tag\n";
+        assert_eq!(find(markdown), vec!["my_tag", "project/esker", "in-a-list"]);
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render("tagged #rust/async."),
+            "<p>tagged <a class=\"tag\" href=\"/tags/rust/async.html\">#rust/async</a>.</p>\n"
+        );
+        assert_eq!(render("**bold**#not-a-tag"), "<p><strong>bold</strong>#not-a-tag</p>\n");
+        assert_eq!(render("&lt; #a"), "<p>&lt; <a class=\"tag\" href=\"/tags/a.html\">#a</a></p>\n");
+    }
+
+    #[test]
+    fn test_tag_pages() {
+        let tags = ["Area/Déjà Vu", "C++", "c++", "c", "++", "..", "Index", "c++/x"].map(String::from);
+        let tag_pages = TagPages::new(Some("/tags".to_string()), false, tags.iter());
+        assert_eq!(tag_pages.path("Area"), "area");
+        assert_eq!(tag_pages.path("Area/Déjà Vu"), "area/deja-vu");
+        assert_eq!(tag_pages.path("C++"), "c");
+        assert_eq!(tag_pages.path("c"), "c-2");
+        assert_eq!(tag_pages.path("c++"), "c-3");
+        assert_eq!(tag_pages.path("c++/x"), "c-3/x");
+        assert_eq!(tag_pages.path("++"), "tag");
+        assert_eq!(tag_pages.path(".."), "tag-2");
+        assert_eq!(tag_pages.path("Index"), "index-2");
+        assert_eq!(tag_pages.url("c++").as_deref(), Some("/tags/c-3.html"));
+        assert_eq!(tag_pages.path("not/used yet"), "not/used-yet");
+
+        let tag_pages = TagPages::new(Some("/tags".to_string()), true, tags.iter());
+        assert_eq!(tag_pages.html_path("c++/x"), "c-3/x/index.html");
+        assert_eq!(tag_pages.url("c++/x").as_deref(), Some("/tags/c-3/x/"));
+        assert_eq!(tag_pages.file_url("c", "page/2/index.html").as_deref(), Some("/tags/c-2/page/2/"));
+        assert_eq!(tag_pages.file_url("c", "atom.xml").as_deref(), Some("/tags/c-2/atom.xml"));
+        assert_eq!(tag_pages.index_url().as_deref(), Some("/tags/"));
+    }
+}
@@ -0,0 +1,35 @@
+//! Pages left at the old urls of a page (its aliases), sending browsers and search engines
+//! on to where it is now.
+
+/// a page that redirects to `url` right away, and tells search engines `url` is the one to keep.
+pub fn html(url: &str) -> String {
+    let url = html_escape::encode_double_quoted_attribute(url);
+    format!(
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n  \
+         <meta charset=\"utf-8\">\n  \
+         <title>Redirecting to {url}</title>\n  \
+         <link rel=\"canonical\" href=\"{url}\">\n  \
+         <meta name=\"robots\" content=\"noindex\">\n  \
+         <meta http-equiv=\"refresh\" content=\"0; url={url}\">\n\
+         </head>\n\
+         <body>\n  \
+         <a href=\"{url}\">This page has moved to {url}</a>\n\
+         </body>\n\
+         </html>\n",
+        url = url
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html() {
+        let html = html("https://example.com/notes/a.html?x=1&y=\"2\"");
+        assert!(html.contains("<link rel=\"canonical\" href=\"https://example.com/notes/a.html?x=1&amp;y=&quot;2&quot;\">"));
+        assert!(html.contains("<meta http-equiv=\"refresh\" content=\"0; url=https://example.com/notes/a.html?x=1&amp;y=&quot;2&quot;\">"));
+    }
+}
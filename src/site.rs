@@ -1,7 +1,7 @@
 use colored::*;
 use hotwatch::Event;
 use slugify::slugify;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 use std::process::Command;
@@ -9,18 +9,24 @@ use std::{env, fs::create_dir_all, path::PathBuf};
 use syntect::html;
 
 use glob::glob;
+use rayon::prelude::*;
 
 use crate::parser::syntax_highlight::THEMES;
 
-use crate::templates::{self, Page};
+use crate::parser::tags::TagPages;
+use crate::templates::{self, IndexPage, Page, SectionNode, SectionPage, TagInfo, TagNode};
 use crate::{Cli, Commands};
 use crate::{config::Config, util};
 use crate::{
-    errors::Errors,
+    errors::{DiagnosticsFormat, Errors, Kind},
+    feeds::{self, FeedContent},
     frontmatter::Frontmatter,
     link::{Link, SiteLinks},
+    live_reload::Reload,
     md_file::MdFile,
     new_site,
+    parser::{links::VaultIndex, ParsedMarkdown},
+    redirects, sitemap,
 };
 
 #[derive(Debug)]
@@ -55,10 +61,24 @@ pub struct Site {
     pub tera: tera::Tera,
     /// links: internal and external
     pub links: SiteLinks,
+    /// every published file in the vault, for resolving links between them
+    pub vault_index: VaultIndex,
     /// user config stuff
     pub config: Config,
-    /// All tags, as collected from frontmatter (TODO: not from content yet!)
+    /// All tags, as collected from frontmatter and inline #tags
     pub tags: HashMap<String, Vec<Link>>,
+    /// The same tags as a tree, split on "/" (for nested tags like #area/health).
+    pub tag_tree: Vec<templates::TagNode>,
+    /// Every tag with its url and how often it's used, for tag clouds.
+    pub tag_list: Vec<templates::TagInfo>,
+    /// Where each tag's page is.
+    pub tag_pages: TagPages,
+    /// The folders of the vault as a tree, with the pages in each.
+    pub section_tree: SectionNode,
+    /// The folders that get a generated index page (with `auto_index`).
+    index_pages: BTreeSet<String>,
+    /// The files that have (or had) the same url as another file.
+    url_collisions: HashSet<PathBuf>,
     /// Sitemap of links to be injected into the Tera context.
     pub template_sitemap: Vec<Link>,
     pub attachments: Vec<Link>,
@@ -79,7 +99,10 @@ impl Site {
 
         let esker_dir = cwd.join("_esker");
         let dir_esker_build = esker_dir.join("_site");
-        let user_config = Config::new(&cwd, &cmd);
+        let user_config = Config::new(&cwd, &cmd).unwrap_or_else(|e| {
+            println!("{}", e);
+            util::exit()
+        });
 
         let mut dir_esker_tags: Option<PathBuf>;
         if let Some(tags_dir) = &user_config.tags_url {
@@ -98,7 +121,14 @@ impl Site {
         }
 
         let (dir_esker_templates, dir_esker_public) =
-            Site::get_possible_theme_paths(&user_config, &esker_dir);
+            Site::get_possible_theme_paths(&user_config, &esker_dir).unwrap_or_else(|e| {
+                println!("{}", e);
+                util::exit()
+            });
+        let tera = crate::templates::load_templates(&dir_esker_templates).unwrap_or_else(|e| {
+            println!("\nError: {}\n", e);
+            util::exit()
+        });
 
         Site {
             dir: cwd,
@@ -114,10 +144,17 @@ impl Site {
             dir_esker: esker_dir,
             dir_esker_site_tags: dir_esker_tags,
             errors: Errors::new(),
-            tera: crate::templates::load_templates(&dir_esker_templates),
+            tera,
             config: user_config,
             links: SiteLinks::new(),
+            vault_index: VaultIndex::new(),
             tags: HashMap::new(),
+            tag_tree: Vec::new(),
+            tag_list: Vec::new(),
+            section_tree: SectionNode::default(),
+            index_pages: BTreeSet::new(),
+            url_collisions: HashSet::new(),
+            tag_pages: TagPages::default(),
             template_sitemap: Vec::new(),
             attachments: Vec::new(),
             cli,
@@ -125,7 +162,7 @@ impl Site {
         }
     }
 
-    fn get_possible_theme_paths(cfg: &Config, dir_esker: &Path) -> (PathBuf, PathBuf) {
+    fn get_possible_theme_paths(cfg: &Config, dir_esker: &Path) -> Result<(PathBuf, PathBuf), String> {
         let templates = dir_esker.join("templates");
         let public = dir_esker.join("public");
 
@@ -135,74 +172,564 @@ impl Site {
             let theme_templates = theme_folder.join("templates");
 
             if !theme_folder.is_dir() {
-                println!(
+                return Err(format!(
                     "The theme: '{}' does not exist in your _esker folder",
                     theme
-                );
-                util::exit()
+                ));
             }
 
             if theme_public.is_dir() && theme_templates.is_dir() {
-                return (theme_templates, theme_public);
+                Ok((theme_templates, theme_public))
             } else {
-                println!(
+                Err(format!(
                     "Please ensure that theme '{}' has a 'public' and 'templates' directory",
                     theme
-                );
-                util::exit()
+                ))
             }
             // is the theme valid? -> check if there /templates and /public
         } else {
-            return (templates, public);
+            Ok((templates, public))
         }
     }
 
     pub fn build(&mut self) {
         self.create_required_directories_for_build();
         self.load_files();
+        self.check_internal_links();
         self.build_tag_pages();
+        self.build_index_pages();
         self.create_theme_css();
         self.cp_data();
         self.cp_public();
         self.build_syndication_pages();
+        self.build_sitemap();
+        self.build_redirects();
+
+        self.errors.report(self.diagnostics_format(), self.cli.verbose);
+    }
 
-        if self.errors.has_errors() {
-            self.errors.report_errors(self.cli.verbose);
+    /// how the build's diagnostics are printed, set with `esker build --diagnostics-format`.
+    pub fn diagnostics_format(&self) -> DiagnosticsFormat {
+        match self.cli_command {
+            Commands::Build { diagnostics_format, .. } => diagnostics_format,
+            _ => DiagnosticsFormat::Human,
         }
     }
 
     fn clear_site_for_rebuild(&mut self) {
         self.errors.clear();
+        self.links.clear();
         self.markdown_files.clear();
         self.markdown_files_paths.clear();
         self.invalid_files.clear();
         self.tags.clear();
+        self.tag_tree.clear();
+        self.tag_list.clear();
+        self.tag_pages = TagPages::default();
+        self.section_tree = SectionNode::default();
         self.template_sitemap.clear();
-
+        self.vault_index.clear();
     }
 
     fn rebuild(&mut self) {
+        let mut errors = Errors::new();
+        self.reload_config_and_templates(&mut errors);
 
-        // reload config
-        self.config = Config::new(&self.dir, &self.cli_command);
-        let (dir_esker_templates, dir_esker_public) =
-            Site::get_possible_theme_paths(&self.config, &self.dir_esker);
-        self.dir_esker_templates = dir_esker_templates;
-        self.dir_esker_public = dir_esker_public;
-
-        fs::remove_dir_all(&self.dir_esker_site).expect("failed to delete _site");
+        if self.dir_esker_site.is_dir() {
+            fs::remove_dir_all(&self.dir_esker_site).expect("failed to delete _site");
+        }
 
         // rebuild
         self.clear_site_for_rebuild();
-        self.tera = crate::templates::load_templates(&self.dir_esker_templates);
+        self.errors.append(&mut errors);
         self.build();
     }
 
-    fn rebuild_markdown(&mut self) {
+    /// reloads the config and templates. If either is broken, the ones in use are kept
+    /// (so `esker watch` keeps going until they're fixed) and the problem is reported.
+    fn reload_config_and_templates(&mut self, errors: &mut Errors) {
+        let config_path = util::path_to_string(&Config::path(&self.dir));
+        let config = Config::new(&self.dir, &self.cli_command)
+            .and_then(|config| Ok((Site::get_possible_theme_paths(&config, &self.dir_esker)?, config)));
+        let ((dir_esker_templates, dir_esker_public), config) = match config {
+            Ok(config) => config,
+            Err(e) => return errors.add_invalid_config(config_path, &e),
+        };
+
+        match crate::templates::load_templates(&dir_esker_templates) {
+            Ok(tera) => self.tera = tera,
+            Err(e) => return errors.add_invalid_templates(util::path_to_string(&dir_esker_templates), &e),
+        }
+        self.config = config;
+        self.dir_esker_templates = dir_esker_templates;
+        self.dir_esker_public = dir_esker_public;
+    }
+
+    /// parses and renders every markdown file again; `errors` are the problems found
+    /// before deciding to, as the site's diagnostics are cleared.
+    fn rebuild_markdown(&mut self, mut errors: Errors) {
         self.clear_site_for_rebuild();
+        self.errors.append(&mut errors);
         self.load_files();
+        self.check_internal_links();
+        self.build_tag_pages();
+        self.build_index_pages();
+        self.build_syndication_pages();
+        self.build_sitemap();
+        self.build_redirects();
+        self.errors.report(self.diagnostics_format(), self.cli.verbose);
+    }
+
+    /// Rebuilds what a change to a single markdown file affects, instead of the whole site:
+    /// the file itself, the pages that embed it or whose links might now point elsewhere,
+    /// and the pages whose backlinks or section listing change. When the file's title, date
+    /// or tags change, every page is rendered again (but not parsed) as they all get the
+    /// tags and sitemap in their context.
+    pub fn rebuild_changed_markdown(&mut self, path: &Path) {
+        // diagnostics are found again for whatever is loaded, parsed or rendered again below,
+        // the ones for the rest of the site are kept.
+        let changed_file = util::path_to_string(path);
+        self.errors.retain(|d| d.file != changed_file);
+        let mut markdown_files = std::mem::take(&mut self.markdown_files);
+        let attachments_before = self.attachments.len();
+        let tag_pages_before = self.tag_pages.clone();
+
+        let (old, mut new) = self.reload_file(&mut markdown_files, path);
+        let new_aliases = new.as_ref().map(|new| new.aliases.clone()).unwrap_or_default();
+        if self.needs_full_rebuild(&markdown_files, path, &tag_pages_before, old.as_ref(), new.as_ref()) {
+            self.markdown_files = markdown_files;
+            let mut errors = Errors::new();
+            self.remove_unused_tag_pages(&tag_pages_before, &mut errors);
+            if let Some(old) = &old {
+                // (a page moved by its permalink or slug leaves its html behind)
+                if new.as_ref().is_none_or(|new| new.out_path != old.out_path) {
+                    self.remove_file(&old.out_path, &mut errors);
+                }
+                self.remove_unused_redirects(old, &new_aliases, &mut errors);
+            }
+            return self.rebuild_markdown(errors);
+        }
+        let mut globals_changed = globals_changed(old.as_ref(), new.as_ref());
+
+        let mut to_parse = pages_to_parse(&markdown_files, path, old.as_ref(), new.as_ref());
+        let mut to_render = ToRender::default();
+        let mut errors = Errors::new();
+
+        match (&old, new.as_mut()) {
+            // carry over what the file linked to before, to know whose backlinks change.
+            (Some(old), Some(new)) => new.dependencies = old.dependencies.clone(),
+            (Some(old), None) => {
+                self.links.remove_from_page(&old.full_url);
+                to_render.urls.extend(old.dependencies.links_to.iter().cloned());
+                to_render.sections.insert(old.web_path_parents.clone());
+                self.remove_file(&old.out_path, &mut errors);
+                // with clean urls, the page had a directory of its own: my-note/index.html
+                if self.clean_urls() && !old.is_section {
+                    if let Some(dir) = old.out_path.parent() {
+                        let _ = fs::remove_dir(dir);
+                    }
+                }
+            }
+            _ => (),
+        }
+
+        if let Some(new) = new {
+            to_parse.insert(new.path.clone());
+            markdown_files.entry(new.web_path_parents.clone()).or_default().push(new);
+        }
+
+        self.reparse_pages(&mut markdown_files, &to_parse, &mut to_render);
+        // every page has its section (and the whole tree), so a change to it renders them all.
+        globals_changed |= self.update_sections(&markdown_files);
+        self.render_pages(&mut markdown_files, &to_render, globals_changed);
+        self.markdown_files = markdown_files;
+
+        if globals_changed {
+            self.remove_unused_tag_pages(&tag_pages_before, &mut errors);
+        }
+        if let Some(old) = &old {
+            self.remove_unused_redirects(old, &new_aliases, &mut errors);
+        }
+        self.errors.append(&mut errors);
+        self.remove_site_wide_diagnostics();
+        self.check_internal_links();
         self.build_tag_pages();
+        self.build_index_pages();
         self.build_syndication_pages();
+        self.build_sitemap();
+        self.build_redirects();
+
+        if self.attachments.len() > attachments_before {
+            self.cp_data();
+        }
+        self.errors.report(self.diagnostics_format(), self.cli.verbose);
+    }
+
+    /// drops the diagnostics of what is checked or built from the whole site (links, tag and
+    /// index pages, feeds...) when only part of it was rebuilt, as they are all found again.
+    fn remove_site_wide_diagnostics(&mut self) {
+        let templates = util::path_to_string(&self.dir_esker_templates);
+        let pages: HashSet<String> = self
+            .markdown_files
+            .values()
+            .flatten()
+            .map(|f| util::path_to_string(&f.out_path))
+            .collect();
+
+        self.errors.retain(|d| match d.kind {
+            Kind::BrokenLink | Kind::AliasCollision => false,
+            Kind::TemplateRender => !d.file.starts_with(&templates),
+            Kind::WriteFailed => pages.contains(&d.file),
+            _ => true,
+        });
+    }
+
+    /// whether a change to a file reaches further than the pages `rebuild_changed_markdown`
+    /// finds, so every page has to be parsed again.
+    fn needs_full_rebuild(
+        &self,
+        markdown_files: &HashMap<PathBuf, Vec<MdFile>>,
+        path: &Path,
+        tag_pages_before: &TagPages,
+        old: Option<&MdFile>,
+        new: Option<&MdFile>,
+    ) -> bool {
+        // a new tag can take the page of one already linked to from other pages ("C++" and
+        // "c++" are both "c.html", whichever sorts first keeps it).
+        let tag_moved = tag_pages_before
+            .paths()
+            .iter()
+            .any(|(tag, path)| self.tag_pages.paths().get(tag).is_some_and(|now| now != path));
+        // a new permalink or slug moves the page, and changes the links to it.
+        let page_moved = matches!((old, new), (Some(old), Some(new)) if old.web_path != new.web_path);
+        // which file gets a url that more than one wants depends on all of them, as does
+        // whether another file can have the url of one that was removed.
+        let new_collides = new.is_some_and(|new| {
+            let key = util::path_to_string(&new.web_path).to_lowercase();
+            markdown_files
+                .values()
+                .flatten()
+                .any(|f| util::path_to_string(&f.web_path).to_lowercase() == key)
+        });
+        tag_moved || page_moved || new_collides || self.url_collisions.contains(path)
+    }
+
+    /// deletes the redirects from the aliases a changed file no longer has, unless a page
+    /// is there now.
+    fn remove_unused_redirects(&self, old: &MdFile, new_aliases: &[PathBuf], errors: &mut Errors) {
+        for alias in old.aliases.iter().filter(|alias| !new_aliases.contains(alias)) {
+            if !self.markdown_files.values().flatten().any(|f| f.web_path == *alias) {
+                self.remove_file(&self.dir_esker_site.join(alias), errors);
+            }
+        }
+    }
+
+    /// takes the old version of a changed file out of `markdown_files` and loads the new one
+    /// (either can be missing: the file was added, removed or (un)published), updating the
+    /// vault index, tags and sitemap. The new version is added back by the caller.
+    fn reload_file(
+        &mut self,
+        markdown_files: &mut HashMap<PathBuf, Vec<MdFile>>,
+        path: &Path,
+    ) -> (Option<MdFile>, Option<MdFile>) {
+        let old = markdown_files.values_mut().find_map(|files| {
+            let position = files.iter().position(|f| f.path == path)?;
+            Some(files.remove(position))
+        });
+        let new = self.load_md_file(path);
+
+        if let Some(old) = &old {
+            self.vault_index.remove(&vault_path(old));
+        }
+        if let Some(new) = &new {
+            self.vault_index.insert(new);
+        }
+        self.update_tags_and_sitemap(old.as_ref(), new.as_ref());
+        (old, new)
+    }
+
+    /// parses the pages in `to_parse` again, noting them to be rendered, along with their
+    /// section and, if their links changed, the pages whose backlinks change.
+    fn reparse_pages(
+        &mut self,
+        markdown_files: &mut HashMap<PathBuf, Vec<MdFile>>,
+        to_parse: &HashSet<PathBuf>,
+        to_render: &mut ToRender,
+    ) {
+        for md_file in markdown_files.values_mut().flatten() {
+            if !to_parse.contains(&md_file.path) {
+                continue;
+            }
+
+            let links_before = self.links.remove_from_page(&md_file.full_url);
+            let links_to_before = md_file.dependencies.links_to.clone();
+            let file = md_file.frontmatter.get_filepath_as_str();
+            self.errors.retain(|d| !(d.file == file && d.kind.is_from_parsing()));
+            let parsed = md_file.parse_markdown_to_html(self);
+            self.add_parsed_markdown(parsed);
+
+            let links_after: Vec<&Link> = self
+                .links
+                .internal
+                .iter()
+                .filter(|link| link.originating_file_url.as_deref() == Some(&md_file.full_url))
+                .collect();
+            if links_before.iter().ne(links_after) {
+                to_render.urls.extend(links_to_before);
+                to_render.urls.extend(md_file.dependencies.links_to.iter().cloned());
+            }
+
+            to_render.files.insert(md_file.path.clone());
+            to_render.sections.insert(md_file.web_path_parents.clone());
+        }
+    }
+
+    /// renders the pages whose content, backlinks or section listing changed, or every page
+    /// when `globals_changed`.
+    fn render_pages(
+        &mut self,
+        markdown_files: &mut HashMap<PathBuf, Vec<MdFile>>,
+        to_render: &ToRender,
+        globals_changed: bool,
+    ) {
+        let sections: HashMap<PathBuf, Vec<MdFile>> = if globals_changed {
+            markdown_files.clone()
+        } else {
+            to_render
+                .sections
+                .iter()
+                .filter_map(|dir| Some((dir.clone(), markdown_files.get(dir)?.clone())))
+                .collect()
+        };
+
+        let mut errors = Errors::new();
+        for md_file in markdown_files.values_mut().flatten() {
+            let should_render = globals_changed
+                || to_render.files.contains(&md_file.path)
+                || to_render.urls.contains(&md_file.full_url)
+                || (md_file.is_section && sections.contains_key(&md_file.web_path_parents));
+
+            if should_render {
+                let file = md_file.frontmatter.get_filepath_as_str();
+                let out_path = util::path_to_string(&md_file.out_path);
+                self.errors.retain(|d| match d.kind {
+                    Kind::TemplateRender => d.file != file,
+                    Kind::WriteFailed => d.file != out_path,
+                    _ => true,
+                });
+                md_file.get_backlinks_for_file(self);
+                md_file.get_related_files(self);
+                if md_file.is_section {
+                    md_file.write_section_html(self, &sections, &mut errors);
+                } else {
+                    md_file.write_html(self, &mut errors);
+                }
+            }
+        }
+        self.errors.append(&mut errors);
+    }
+
+    /// loads a markdown file, if it is one that gets published.
+    fn load_md_file(&mut self, path: &Path) -> Option<MdFile> {
+        let path = path.to_path_buf();
+        if !path.is_file() || !self.is_in_private_folder(&path) {
+            return None;
+        }
+
+        let fm = Frontmatter::new(&mut self.errors, &path)?;
+        let raw = fs::read_to_string(&path).ok()?;
+        let md_file = MdFile::new(self, raw, path, fm);
+        md_file.frontmatter.publish.then_some(md_file)
+    }
+
+    /// swaps the tag and sitemap links of the old version of a file for the new one,
+    /// keeping their place so the order of tag pages and the sitemap doesn't change.
+    fn update_tags_and_sitemap(&mut self, old: Option<&MdFile>, new: Option<&MdFile>) {
+        if let Some(old) = old {
+            for (tag, links) in self.tags.iter_mut() {
+                let Some(position) = links.iter().position(|link| link.url == old.full_url) else {
+                    continue;
+                };
+                match new {
+                    Some(new) if new.tags.contains(tag) => {
+                        links[position] = Link::new_tag_link_from_md_file(new)
+                    }
+                    _ => {
+                        links.remove(position);
+                    }
+                }
+            }
+            self.tags.retain(|_tag, links| !links.is_empty());
+
+            let position = self.template_sitemap.iter().position(|link| link.url == old.full_url);
+            match (position, new.filter(|new| new.frontmatter.in_sitemap)) {
+                (Some(position), Some(new)) => self.template_sitemap[position] = Link::new_sitemap_link(new),
+                (Some(position), None) => {
+                    self.template_sitemap.remove(position);
+                }
+                (None, _) => (),
+            }
+        }
+
+        if let Some(new) = new {
+            for tag in &new.tags {
+                let links = self.tags.entry(tag.clone()).or_default();
+                if !links.iter().any(|link| link.url == new.full_url) {
+                    links.push(Link::new_tag_link_from_md_file(new));
+                }
+            }
+            if new.frontmatter.in_sitemap && !self.template_sitemap.iter().any(|link| link.url == new.full_url) {
+                self.template_sitemap.push(Link::new_sitemap_link(new));
+            }
+        }
+        self.update_tag_tree();
+    }
+
+    /// works out where every tag's page is, and the tree and list of tags for templates.
+    /// Templates get the same urls through the `tag_url` filter: {{ "my tag" | tag_url }}
+    fn update_tag_tree(&mut self) {
+        self.tag_pages = TagPages::new(crate::parser::tags::tag_pages_url(self), self.clean_urls(), self.tags.keys());
+        self.tag_tree = TagNode::tree(&self.tags, &self.tag_pages);
+        self.tag_list = TagInfo::list(&self.tags, &self.tag_pages);
+
+        let tag_pages = self.tag_pages.clone();
+        self.tera.register_filter("tag_url", move |value: &tera::Value, _: &HashMap<String, tera::Value>| {
+            let tag = tera::from_value::<String>(value.clone())
+                .map_err(|_| tera::Error::msg("the tag_url filter only works on a tag (a string)"))?;
+            Ok(tera::to_value(tag_pages.url(&tag).unwrap_or_default())?)
+        });
+    }
+
+    /// builds the section tree, and the `get_section` and `get_page` functions that let a
+    /// template look up any section or page by its path in the vault:
+    /// {% set notes = get_section(path="notes") %} {% set about = get_page(path="about.md") %}
+    /// Returns whether the tree changed, apart from when pages were last updated (see `SectionNode::same_listing`).
+    fn update_sections(&mut self, markdown_files: &HashMap<PathBuf, Vec<MdFile>>) -> bool {
+        let mut section_tree = SectionNode::tree(markdown_files, &self.config.title);
+        let index_pages: BTreeSet<String> = match self.config.auto_index.unwrap_or(false) {
+            true => section_tree
+                .set_index_urls(&|path| self.build_page_url(&Path::new(path).join("index.html")))
+                .into_iter()
+                .collect(),
+            false => BTreeSet::new(),
+        };
+        // folders that no longer get one: they're gone, or their _index.md now takes its place.
+        let mut errors = Errors::new();
+        for path in self.index_pages.difference(&index_pages) {
+            if section_tree.find(path).is_some_and(|section| section.url.is_some()) {
+                continue;
+            }
+            let dir = self.dir_esker_site.join(path);
+            self.remove_file(&dir.join("index.html"), &mut errors);
+            crate::md_file::remove_pages_after(&dir, 1);
+        }
+        self.errors.append(&mut errors);
+        self.index_pages = index_pages;
+        let changed = !section_tree.same_listing(&self.section_tree);
+
+        let sections: HashMap<String, tera::Value> = section_tree
+            .descendants()
+            .into_iter()
+            .filter_map(|section| Some((section.path.clone(), tera::to_value(section).ok()?)))
+            .collect();
+        let mut pages: HashMap<String, tera::Value> = HashMap::new();
+        // a page wins over a section's _index.md with the same path (notes.md and notes/_index.md).
+        let mut md_files: Vec<&MdFile> = markdown_files.values().flatten().collect();
+        md_files.sort_by_key(|md_file| !md_file.is_section);
+        for md_file in md_files {
+            let path = templates::content_path(&util::path_to_string(&md_file.vault_path()));
+            if let Ok(page) = tera::to_value(Page::new(md_file)) {
+                pages.insert(path, page);
+            }
+        }
+
+        self.tera.register_function("get_section", move |args: &HashMap<String, tera::Value>| {
+            let path = lookup_path("get_section", args)?;
+            sections
+                .get(&path)
+                .cloned()
+                .ok_or_else(|| tera::Error::msg(format!("get_section: there is no section at '{}'", path)))
+        });
+        self.tera.register_function("get_page", move |args: &HashMap<String, tera::Value>| {
+            let path = lookup_path("get_page", args)?;
+            pages
+                .get(&path)
+                .cloned()
+                .ok_or_else(|| tera::Error::msg(format!("get_page: there is no page at '{}'", path)))
+        });
+
+        self.section_tree = section_tree;
+        changed
+    }
+
+    /// with `auto_index`, writes an index page for every folder with pages but no _index.md,
+    /// from list.html: given the folder as `section` (and a `page` with its title and url),
+    /// like a section page, and paginated the same way.
+    fn build_index_pages(&mut self) {
+        let mut errors = Errors::new();
+        for section in self.section_tree.descendants() {
+            let (true, Some(url)) = (self.index_pages.contains(&section.path), &section.url) else {
+                continue;
+            };
+            let dir = self.dir_esker_site.join(&section.path);
+            let page_url = |current: usize| match current {
+                1 => url.clone(),
+                n => self.build_page_url(&templates::pagination_path(Path::new(&section.path), n)),
+            };
+            let paginators = templates::Paginator::paginate(&section.pages, self.config.paginate_by, page_url);
+
+            for paginator in &paginators {
+                let mut ctx = tera::Context::new();
+                ctx.insert("page", &IndexPage::new(section));
+                ctx.insert("pages", paginator.pages);
+                ctx.insert("paginator", paginator);
+                ctx.insert("baseurl", &self.config.url.clone());
+                ctx.insert("section", &SectionPage::new(paginator.pages, section));
+                ctx.insert("section_tree", &self.section_tree);
+                ctx.insert("config", &templates::Config::new(self));
+                ctx.insert("tags", &self.tags);
+                ctx.insert("tag_tree", &self.tag_tree);
+                ctx.insert("tag_list", &self.tag_list);
+                ctx.insert("sitemap", &self.template_sitemap);
+
+                let out_path = match paginator.current {
+                    1 => dir.join("index.html"),
+                    n => templates::pagination_path(&dir, n),
+                };
+                match self.tera.render("list.html", &ctx) {
+                    Ok(rendered_template) => self.write_file(&out_path, rendered_template, &mut errors),
+                    Err(e) => errors.add_template_error(self.template_path("list.html"), &e),
+                }
+            }
+            crate::md_file::remove_pages_after(&dir, paginators.len());
+        }
+        self.errors.append(&mut errors);
+    }
+
+    fn remove_unused_tag_pages(&self, tag_pages_before: &TagPages, errors: &mut Errors) {
+        if let Some(dir_tags) = &self.dir_esker_site_tags {
+            let paths_now: HashSet<&String> = self.tag_pages.paths().values().collect();
+            for (tag, path) in tag_pages_before.paths().iter().filter(|(_tag, path)| !paths_now.contains(path)) {
+                self.remove_file(&dir_tags.join(tag_pages_before.html_path(tag)), errors);
+                crate::md_file::remove_pages_after(&dir_tags.join(path), 1);
+                for feed in ["atom.xml", "feed.json"] {
+                    self.remove_file(&dir_tags.join(path).join(feed), errors);
+                }
+                // (and its directory, if nothing else is in it)
+                let _ = fs::remove_dir(dir_tags.join(path));
+            }
+        }
+    }
+
+    /// deletes a file from _site that is no longer needed, reporting it if that fails
+    /// (one that's already gone is fine).
+    pub fn remove_file(&self, path: &Path, errors: &mut Errors) {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                errors.add_remove_error(util::path_to_string(path), &e)
+            }
+            _ => (),
+        }
     }
 
     fn create_required_directories_for_build(&self) {
@@ -217,7 +744,9 @@ impl Site {
     }
 
     pub fn cp_public(&mut self) {
-        fs::remove_dir_all(&self.dir_esker_site_public).unwrap();
+        if self.dir_esker_site_public.is_dir() {
+            fs::remove_dir_all(&self.dir_esker_site_public).unwrap();
+        }
         create_dir_all(self.dir_esker_site_public.clone()).unwrap();
         Command::new("cp")
             .arg("-n")
@@ -249,30 +778,125 @@ impl Site {
         self.cleanup_unusued_attachments();
     }
 
-    /// build_tag_pages will render html pages for each tag,
-    /// and is given a tera context with access to tagged_items;
-    /// this allows users to generate an html page per tag, that can
-    /// link to each page that is thusly tagged.
-    fn build_tag_pages(&self) {
+    /// build_tag_pages will render an html page for each tag, and for each level of nested
+    /// tags ("area" and "area/health" for #area/health/sleep), from the tags.html template.
+    /// The page is given the `tag_node` with the pages tagged with it and its child tags.
+    /// tags/index.html lists every tag, from tags_index.html (or tags.html, for sites made
+    /// before there was one).
+    fn build_tag_pages(&mut self) {
+        let mut errors = Errors::new();
         if let Some(dir_tags) = &self.dir_esker_site_tags {
             fs::create_dir_all(dir_tags).expect("failed to create tags directory");
 
-            for tag_name in self.tags.keys() {
-                if !tag_name.is_empty() {
-                    let mut ctx = tera::Context::new();
-                    ctx.insert("baseurl", &self.config.url.clone());
-                    ctx.insert("tags", &self.tags);
-                    ctx.insert("config", &templates::Config::new(self));
-                    ctx.insert("tag", &tag_name);
-                    ctx.insert("sitemap", &self.template_sitemap);
-
-                    let tag_file_name = Path::new(tag_name).with_extension("html");
-                    let out_path = dir_tags.join(tag_file_name);
-                    let rendered_template = self.tera.render("tags.html", &ctx).unwrap();
-                    fs::write(out_path, rendered_template).unwrap();
+            let root = TagNode::root(self.tag_tree.clone(), &self.tag_pages);
+            let nodes = root.descendants();
+
+            // templates written for flat tags list `tags[tag]`, so every page has an entry.
+            let mut tags = self.tags.clone();
+            for tag_node in &nodes {
+                tags.entry(tag_node.info.tag.clone()).or_default();
+            }
+
+            let index_template = match self.tera.get_template_names().any(|name| name == "tags_index.html") {
+                true => "tags_index.html",
+                false => "tags.html",
+            };
+            'nodes: for tag_node in nodes {
+                let mut ctx = tera::Context::new();
+                ctx.insert("baseurl", &self.config.url.clone());
+                ctx.insert("tags", &tags);
+                ctx.insert("tag_tree", &self.tag_tree);
+                ctx.insert("tag_list", &self.tag_list);
+                ctx.insert("section_tree", &self.section_tree);
+                ctx.insert("config", &templates::Config::new(self));
+                ctx.insert("tag", &tag_node.info.tag);
+                ctx.insert("tag_node", tag_node);
+                ctx.insert("sitemap", &self.template_sitemap);
+
+                // a tag's pages are split up like a section's: tags/rust/page/2/index.html
+                let tag = tag_node.info.tag.as_str();
+                let (template, out_path, dir, paginate_by) = match tag {
+                    "" => (index_template, dir_tags.join("index.html"), dir_tags.clone(), None),
+                    tag => (
+                        "tags.html",
+                        dir_tags.join(self.tag_pages.html_path(tag)),
+                        dir_tags.join(self.tag_pages.path(tag)),
+                        self.config.paginate_by,
+                    ),
+                };
+                let url = |current: usize| match current {
+                    1 => tag_node.info.url.clone().unwrap_or_default(),
+                    n => {
+                        let page_path = util::path_to_string(&templates::pagination_path(Path::new(""), n));
+                        self.tag_pages.file_url(tag, &page_path).unwrap_or_default()
+                    }
+                };
+                let paginators = templates::Paginator::paginate(&tag_node.pages, paginate_by, url);
+
+                for paginator in &paginators {
+                    ctx.insert("paginator", paginator);
+                    let out_path = match paginator.current {
+                        1 => out_path.clone(),
+                        n => templates::pagination_path(&dir, n),
+                    };
+                    match self.tera.render(template, &ctx) {
+                        Ok(rendered_template) => self.write_file(&out_path, rendered_template, &mut errors),
+                        // every tag page fails the same way, once is enough.
+                        Err(e) => {
+                            errors.add_template_error(self.template_path(template), &e);
+                            match template {
+                                "tags.html" => break 'nodes,
+                                _ => break,
+                            }
+                        }
+                    }
                 }
+                if !tag.is_empty() {
+                    crate::md_file::remove_pages_after(&dir, paginators.len());
+                }
+            }
+        }
+        self.errors.append(&mut errors);
+    }
+
+    /// where a template lives, for reporting errors with it.
+    fn template_path(&self, template_name: &str) -> String {
+        util::path_to_string(&self.dir_esker_templates.join(template_name))
+    }
+
+    /// writes a file to _site, reporting it if that fails.
+    pub fn write_file(&self, out_path: &Path, contents: String, errors: &mut Errors) {
+        let written = match out_path.parent() {
+            Some(parent) => fs::create_dir_all(parent).and_then(|_| fs::write(out_path, contents)),
+            None => fs::write(out_path, contents),
+        };
+        if let Err(e) = written {
+            errors.add_write_error(util::path_to_string(out_path), &e);
+        }
+    }
+
+    /// writes a page redirecting to each page from each of its aliases. Aliases at the url
+    /// of a page (or an earlier alias) are reported, and don't get a redirect.
+    fn build_redirects(&mut self) {
+        let mut errors = Errors::new();
+        let mut files: Vec<&MdFile> = self.markdown_files.values().flatten().collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        // (every page has a url of its own, see `resolve_url_collisions`)
+        let mut taken: HashMap<&Path, &MdFile> = files.iter().map(|f| (f.web_path.as_path(), *f)).collect();
+
+        for f in &files {
+            for alias in &f.aliases {
+                if let Some(other) = taken.get(alias.as_path()) {
+                    let other = other.frontmatter.get_filepath_as_str();
+                    errors.add_alias_collision(f.frontmatter.get_filepath_as_str(), &self.build_page_url(alias), &other);
+                    continue;
+                }
+                taken.insert(alias, f);
+                self.write_file(&self.dir_esker_site.join(alias), redirects::html(&f.full_url), &mut errors);
             }
         }
+        self.errors.append(&mut errors);
     }
 
     /// responsible for rendering a feed.rss template using tera.
@@ -286,21 +910,181 @@ impl Site {
             }
         }
 
-        all_pages.sort_by(|a, b| b.date_created_timestamp.cmp(&a.date_created_timestamp));
+        // (by url too, so pages created at the same time don't swap places between builds)
+        all_pages.sort_by(|a, b| {
+            b.date_created_timestamp
+                .cmp(&a.date_created_timestamp)
+                .then_with(|| a.url.cmp(b.url))
+        });
 
         let mut ctx = tera::Context::new();
 
         ctx.insert("config", &templates::Config::new(self));
         ctx.insert("pages", &all_pages);
+        ctx.insert("section_tree", &self.section_tree);
+
+        let mut errors = Errors::new();
+        match self.tera.render("feed.rss", &ctx) {
+            Ok(rendered_template) => {
+                let out_path = self.dir_esker_site.join("feed.rss");
+                self.write_file(&out_path, rendered_template, &mut errors);
+            }
+            Err(e) => errors.add_template_error(self.template_path("feed.rss"), &e),
+        }
+        self.errors.append(&mut errors);
+        self.build_feeds();
+    }
+
+    /// writes atom.xml and feed.json for the whole site and, if the config asks for them,
+    /// for each section (<section>/atom.xml) and tag (<tags_url>/<tag>/atom.xml).
+    fn build_feeds(&mut self) {
+        let mut errors = Errors::new();
+        let pages: Vec<&MdFile> = self.markdown_files.values().flatten().filter(|f| !f.is_section).collect();
+
+        let mut feeds = vec![(
+            self.dir_esker_site.clone(),
+            self.feed(
+                self.config.title.clone(),
+                self.config.url.clone(),
+                |file_name| self.build_with_baseurl(file_name.to_string()),
+                pages.clone(),
+            ),
+        )];
 
-        let rendered_template = self.tera.render("feed.rss", &ctx).unwrap();
-        let out_path = self.dir_esker_site.join("feed.rss");
-        fs::write(out_path, rendered_template).unwrap();
+        if self.config.feeds.sections {
+            for section in self.markdown_files.values().flatten().filter(|f| f.is_section) {
+                let dir = &section.web_path_parents;
+                if dir.as_os_str().is_empty() {
+                    continue;
+                }
+                let section_pages = pages.iter().filter(|f| f.web_path_parents.starts_with(dir)).copied().collect();
+                // next to the section's page, which a permalink can put somewhere else.
+                let html_dir = section.web_path.parent().unwrap_or(Path::new(""));
+                let dir_url = url_escape::encode_path(&util::path_to_string(html_dir)).to_string();
+                feeds.push((
+                    self.dir_esker_site.join(html_dir),
+                    self.feed(
+                        format!("{} - {}", section.frontmatter.title, self.config.title),
+                        url_escape::encode_path(&section.full_url).to_string(),
+                        |file_name| self.build_with_baseurl(format!("{}/{}", dir_url, file_name)),
+                        section_pages,
+                    ),
+                ));
+            }
+        }
+
+        if let (true, Some(dir_tags)) = (self.config.feeds.tags, &self.dir_esker_site_tags) {
+            for tag_node in self.tag_tree.iter().flat_map(TagNode::descendants) {
+                // a tag's feed has the pages tagged with any tag nested under it too.
+                let tag = &tag_node.info.tag;
+                let tag_pages = pages
+                    .iter()
+                    .filter(|f| f.tags.iter().any(|t| t == tag || t.starts_with(&format!("{}/", tag))))
+                    .copied()
+                    .collect();
+                feeds.push((
+                    dir_tags.join(self.tag_pages.path(tag)),
+                    self.feed(
+                        format!("#{} - {}", tag, self.config.title),
+                        tag_node.info.url.clone().unwrap_or_default(),
+                        |file_name| self.tag_pages.file_url(tag, file_name).unwrap_or_default(),
+                        tag_pages,
+                    ),
+                ));
+            }
+        }
+
+        for (dir, feed) in feeds {
+            self.write_file(&dir.join("atom.xml"), feeds::atom(&feed), &mut errors);
+            self.write_file(&dir.join("feed.json"), feeds::json(&feed), &mut errors);
+        }
+        self.errors.append(&mut errors);
+    }
+
+    /// a feed of the newest pages (as many as the config allows).
+    fn feed(
+        &self,
+        title: String,
+        home_page_url: String,
+        feed_url: impl Fn(&str) -> String,
+        mut pages: Vec<&MdFile>,
+    ) -> feeds::Feed {
+        pages.sort_by(|a, b| MdFile::newest_first(a, b));
+        if let Some(limit) = self.config.feeds.limit {
+            pages.truncate(limit);
+        }
+
+        let items = pages
+            .into_iter()
+            .map(|f| feeds::Item {
+                // feeds only take valid urls: "My Folder/" is "My%20Folder/"
+                url: url_escape::encode_path(&f.full_url).to_string(),
+                title: f.frontmatter.title.clone(),
+                summary: f.frontmatter.summary.clone(),
+                content_html: match self.config.feeds.content {
+                    FeedContent::Full => Some(f.html.clone()),
+                    FeedContent::Summary => None,
+                },
+                date_published: f.frontmatter.date_created,
+                date_updated: f.frontmatter.date_updated,
+                tags: f.tags.clone(),
+            })
+            .collect();
+        feeds::Feed {
+            title,
+            description: self.config.description.clone(),
+            author: self.config.title.clone(),
+            home_page_url,
+            atom_url: feed_url("atom.xml"),
+            json_url: feed_url("feed.json"),
+            items,
+        }
+    }
+
+    /// writes sitemap.xml, listing every published page that isn't left out with
+    /// `in_sitemap: false` (and the `auto_index` pages of folders, updated with their newest
+    /// page), and a robots.txt pointing to it if the config asks for one.
+    fn build_sitemap(&mut self) {
+        let mut entries: Vec<sitemap::Entry> = self
+            .markdown_files
+            .values()
+            .flatten()
+            .filter(|md_file| md_file.frontmatter.in_sitemap)
+            .map(|md_file| sitemap::Entry {
+                url: md_file.full_url.clone(),
+                date_updated: md_file.frontmatter.date_updated,
+            })
+            .collect();
+        for section in self.section_tree.descendants() {
+            let (true, Some(url)) = (self.index_pages.contains(&section.path), &section.url) else {
+                continue;
+            };
+            let date_updated = self
+                .markdown_files
+                .iter()
+                .filter(|(dir, _files)| dir.starts_with(&section.path))
+                .flat_map(|(_dir, files)| files.iter().map(|f| f.frontmatter.date_updated))
+                .max();
+            if let Some(date_updated) = date_updated {
+                entries.push(sitemap::Entry { url: url.clone(), date_updated });
+            }
+        }
+        entries.sort_by(|a, b| a.url.cmp(&b.url));
+
+        let mut errors = Errors::new();
+        self.write_file(&self.dir_esker_site.join("sitemap.xml"), sitemap::xml(&entries), &mut errors);
+        if self.config.robots_txt.unwrap_or(false) {
+            let robots_txt = sitemap::robots_txt(&self.config.url);
+            self.write_file(&self.dir_esker_site.join("robots.txt"), robots_txt, &mut errors);
+        }
+        self.errors.append(&mut errors);
     }
 
     // Fetches all the file paths with a glob
     // then iterates over them and loads them into the struct's memory.
-    // TODO: break this into multiple functions
+    // Reading, parsing and rendering files is done in parallel; anything collected from
+    // the files (tags, links, errors) is added to the site in the order of their paths,
+    // so the site comes out the same no matter how the work was split up.
     pub fn load_files(&mut self) {
         let markdown_files_paths = util::load_files(&self.dir, "**/*.md");
 
@@ -311,44 +1095,81 @@ impl Site {
         let mut markdown_files: HashMap<PathBuf, Vec<MdFile>> = HashMap::new();
         let mut invalid_files: Vec<PathBuf> = Vec::new();
 
-        // collect all files and push them into the map.
-        markdown_files_paths_filtered.iter().for_each(|f| {
-            if let Some(fm) = Frontmatter::new(self, f) {
-                let read_file = fs::read_to_string(f).expect("Unable to open file");
-                let mut md_file = MdFile::new(self, read_file, f.to_path_buf(), fm);
-
-                if md_file.frontmatter.publish {
-                    if let Some(vec_of_files) = markdown_files.get_mut(&md_file.web_path_parents) {
-                        self.collect_tags_from_frontmatter(&md_file);
-                        self.template_sitemap.push(Link::new_sitemap_link(&md_file));
-                        md_file.parse_markdown_to_html(self);
-                        vec_of_files.push(md_file);
-                    } else {
-                        self.collect_tags_from_frontmatter(&md_file);
-                        markdown_files.insert(md_file.web_path_parents.clone(), vec![md_file]);
-                    }
-                }
-            } else {
-                invalid_files.push(f.to_path_buf());
+        // Loop #1 - read every file and its frontmatter.
+        let site: &Site = self;
+        let loaded: Vec<_> = markdown_files_paths_filtered
+            .par_iter()
+            .map(|f| {
+                let mut errors = Errors::new();
+                let md_file = Frontmatter::new(&mut errors, f).and_then(|fm| {
+                    let read_file = fs::read_to_string(f).ok()?;
+                    Some(MdFile::new(site, read_file, f.to_path_buf(), fm))
+                });
+                (f, md_file, errors)
+            })
+            .collect();
+
+        let mut published: Vec<MdFile> = Vec::new();
+        for (f, md_file, mut errors) in loaded {
+            self.errors.append(&mut errors);
+            match md_file {
+                Some(md_file) if md_file.frontmatter.publish => published.push(md_file),
+                Some(_unpublished) => (),
+                None => invalid_files.push(f.to_path_buf()),
             }
-        });
+        }
 
-        // TODO (i tried, i don't know): not sure how to not have to clone this.
-        let markdown_files_clone = markdown_files.clone();
-
-        // Loop #2 - Let's render it!
-        for vec_md_files in markdown_files.values_mut() {
-            for f in vec_md_files {
-                if f.frontmatter.publish {
-                    if f.is_section {
-                        f.get_backlinks_for_file(self);
-                        f.write_section_html(self, &markdown_files_clone);
-                    } else {
-                        f.get_backlinks_for_file(self);
-                        f.write_html(self);
-                    }
-                }
+        // ... give every file a url of its own, and push them into the map.
+        for md_file in self.resolve_url_collisions(published) {
+            self.collect_tags(&md_file);
+            if md_file.frontmatter.in_sitemap {
+                self.template_sitemap.push(Link::new_sitemap_link(&md_file));
             }
+            self.vault_index.insert(&md_file);
+            markdown_files
+                .entry(md_file.web_path_parents.clone())
+                .or_default()
+                .push(md_file);
+        }
+
+        self.update_tag_tree();
+
+        // Loop #2 - parse the markdown, now that every file is known (wikilinks can point anywhere).
+        let mut files: Vec<&mut MdFile> = markdown_files.values_mut().flatten().collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let site: &Site = self;
+        let parsed: Vec<ParsedMarkdown> = files
+            .par_iter_mut()
+            .map(|f| f.parse_markdown_to_html(site))
+            .collect();
+        for parsed in parsed {
+            self.add_parsed_markdown(parsed);
+        }
+
+        // Loop #3 - Let's render it! Backlinks and related files first, as sections list their pages.
+        let site: &Site = self;
+        files.par_iter_mut().for_each(|f| {
+            f.get_backlinks_for_file(site);
+            f.get_related_files(site);
+        });
+        self.update_sections(&markdown_files);
+        let site: &Site = self;
+        let mut files: Vec<&MdFile> = markdown_files.values().flatten().collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let render_errors: Vec<Errors> = files
+            .par_iter()
+            .map(|f| {
+                let mut errors = Errors::new();
+                if f.is_section {
+                    f.write_section_html(site, &markdown_files, &mut errors);
+                } else {
+                    f.write_html(site, &mut errors);
+                }
+                errors
+            })
+            .collect();
+        for mut errors in render_errors {
+            self.errors.append(&mut errors);
         }
 
         // on completion, we can now store the temporary data structures into self for future ref.
@@ -357,8 +1178,115 @@ impl Site {
         self.invalid_files = invalid_files;
     }
 
-    fn collect_tags_from_frontmatter(&mut self, md_file: &MdFile) {
-        for tag in &md_file.frontmatter.tags {
+    /// finds the files that would be written to the same place as another (slugs are the same
+    /// for "My Note.md" and "my-note.md", and some filesystems ignore case) before anything is
+    /// written. The first file, by path, keeps its url; the others are skipped, or with
+    /// `disambiguate_urls` moved to one of their own: my-note-2.html. Both files are reported.
+    fn resolve_url_collisions(&mut self, mut files: Vec<MdFile>) -> Vec<MdFile> {
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let key = |web_path: &Path| util::path_to_string(web_path).to_lowercase();
+
+        let mut taken: HashMap<String, PathBuf> = HashMap::new();
+        let mut colliding: Vec<(usize, PathBuf)> = Vec::new();
+        for (i, f) in files.iter().enumerate() {
+            match taken.get(&key(&f.web_path)) {
+                Some(other) => colliding.push((i, other.clone())),
+                None => {
+                    taken.insert(key(&f.web_path), f.path.clone());
+                }
+            }
+        }
+
+        let mut errors = Errors::new();
+        let mut skipped: HashSet<usize> = HashSet::new();
+        self.url_collisions.clear();
+        for (i, other) in colliding {
+            let f = &mut files[i];
+            let other_str = other.to_string_lossy().to_string();
+            self.url_collisions.extend([f.path.clone(), other]);
+
+            if !self.config.disambiguate_urls.unwrap_or(false) {
+                errors.add_url_collision(f.frontmatter.get_filepath_as_str(), &f.full_url, &other_str);
+                skipped.insert(i);
+                continue;
+            }
+            let own_dir = f.is_section || self.clean_urls();
+            let web_path = (2..)
+                .map(|n| crate::md_file::with_suffix(&f.web_path, n, own_dir))
+                .find(|web_path| !taken.contains_key(&key(web_path)))
+                .unwrap_or_default();
+            taken.insert(key(&web_path), f.path.clone());
+            let url = std::mem::take(&mut f.full_url);
+            f.set_web_path(self, web_path);
+            errors.add_disambiguated_url(f.frontmatter.get_filepath_as_str(), &url, &other_str, &f.full_url);
+        }
+        self.errors.append(&mut errors);
+
+        files
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _f)| !skipped.contains(i))
+            .map(|(_i, f)| f)
+            .collect()
+    }
+
+    /// adds the links, attachments and errors found when parsing a file to the site.
+    fn add_parsed_markdown(&mut self, mut parsed: ParsedMarkdown) {
+        for link in parsed.links {
+            self.add_link(link);
+        }
+        for attachment in parsed.attachments {
+            self.add_attachment(attachment);
+        }
+        self.errors.append(&mut parsed.errors);
+    }
+
+    /// compares every internal link against the pages that were built (and the headings
+    /// in them), reporting any link that points nowhere.
+    fn check_internal_links(&mut self) {
+        let mut pages: HashMap<&str, &MdFile> = HashMap::new();
+        for md_file in self.markdown_files.values().flatten() {
+            pages.insert(&md_file.full_url, md_file);
+        }
+
+        let mut broken_links = Vec::new();
+        for link in &self.links.internal {
+            if Link::is_mailto(&link.url) {
+                continue;
+            }
+
+            let is_broken = match pages.get(link.url_without_fragment()) {
+                // links to attachments that exist were added to the site's attachments when parsed.
+                None => !self.attachments.iter().any(|a| a.url == link.url_without_fragment()),
+                // ^block-ids don't get an anchor on the page, so there is nothing to check.
+                Some(page) => match link.fragment() {
+                    Some(fragment) if !fragment.starts_with('^') => {
+                        !page.toc.iter().any(|heading| heading.url == link.url)
+                    }
+                    _ => false,
+                },
+            };
+
+            if is_broken {
+                let source = link.originating_file_url.clone().unwrap_or_default();
+                let source = match pages.get(source.as_str()) {
+                    Some(page) => page.frontmatter.get_filepath_as_str(),
+                    None => source,
+                };
+                broken_links.push((source, link.line, link.url.clone(), link.title.clone()));
+            }
+        }
+
+        // wikilinks are collected before markdown links, put them back in the order they're in the file.
+        broken_links.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+        for (source, line, target, text) in broken_links {
+            self.errors.add_broken_link(source, line, &target, &text);
+        }
+    }
+
+    /// adds the file to the pages listed for each of its tags, from its frontmatter or inline.
+    fn collect_tags(&mut self, md_file: &MdFile) {
+        for tag in &md_file.tags {
             let new_tag_link = Link::new_tag_link_from_md_file(md_file);
 
             if let Some(list_of_links_for_tag) = self.tags.get_mut(tag) {
@@ -373,6 +1301,17 @@ impl Site {
         format!("{}/{}", self.config.url, web_path)
     }
 
+    /// the url of a page written to `html_path` in _site: "notes/idea.html", or "notes/idea/"
+    /// for "notes/idea/index.html" with clean urls.
+    pub fn build_page_url(&self, html_path: &Path) -> String {
+        self.build_with_baseurl(util::page_url_path(&util::path_to_string(html_path), self.clean_urls()))
+    }
+
+    /// whether pages are written as my-note/index.html and linked to without ".html".
+    pub fn clean_urls(&self) -> bool {
+        self.config.clean_urls.unwrap_or(false)
+    }
+
     // used to add links to the internal global links list.
     pub fn add_link(&mut self, link: Link) {
         if link.is_internal {
@@ -383,7 +1322,9 @@ impl Site {
     }
 
     pub fn add_attachment(&mut self, link: Link) {
-        self.attachments.push(link);
+        if !self.attachments.contains(&link) {
+            self.attachments.push(link);
+        }
     }
 
     /// filter out files that are in the private folder.
@@ -424,10 +1365,8 @@ impl Site {
                         let attachment_str = &util::path_to_string(&trimmed_attachment_path);
                         let attachment_str_encoded: String = url_escape::encode_fragment(attachment_str).into();
 
-                        if !approved_attachments.contains(&attachment_str_encoded) {
-                            if pathbuf.is_file() {
-                                fs::remove_file(pathbuf).unwrap();
-                            }
+                        if !approved_attachments.contains(&attachment_str_encoded) && pathbuf.is_file() {
+                            fs::remove_file(pathbuf).unwrap();
                         }
                     }
                     Err(_e) => (),
@@ -464,31 +1403,46 @@ impl Site {
         }
     }
 
-    pub fn handle_watch_event(&mut self, event: Event) {
-        if let Event::Write(path) | Event::Create(path) | Event::Remove(path) = event {
+    /// rebuilds whatever a change in the vault affects, returning how the browser
+    /// should be reloaded (if the change was one esker cares about).
+    pub fn handle_watch_event(&mut self, event: Event) -> Option<Reload> {
+        let mut reload = None;
+
+        if let Event::Rename(from, to) = event {
+            reload = self.handle_watch_event(Event::Remove(from));
+            reload = reload.max(self.handle_watch_event(Event::Create(to)));
+        } else if let Event::Rescan = event {
+            self.rebuild_markdown(Errors::new());
+            reload = Some(Reload::Page);
+        } else if let Event::Write(path) | Event::Create(path) | Event::Remove(path) = event {
             // NOTE: this removes the last element if it's a file and removes
             // all prefixing path parents from the current working directory.
             let stripped_path = util::strip_pwd(&self.dir, &path);
 
             if let Some(ext) = path.extension() {
                 if ext == "md" {
-                    self.rebuild_markdown();
+                    self.rebuild_changed_markdown(&path);
+                    reload = Some(Reload::Page);
                 }
             }
 
             // handle public folder
             if path.starts_with(&self.dir_esker_public) {
-                self.cp_public()
+                self.cp_public();
+                let is_css = path.extension().is_some_and(|ext| ext == "css");
+                reload = reload.max(Some(if is_css { Reload::Css } else { Reload::Page }));
             }
 
             // handle templates and config file.
             if path.starts_with(&self.dir_esker_templates) {
-                self.rebuild()
+                self.rebuild();
+                reload = Some(Reload::Page);
             }
 
             if let Some(filename) = path.file_name() {
                 if filename == "config.yaml" {
-                    self.rebuild()
+                    self.rebuild();
+                    reload = Some(Reload::Page);
                 }
             }
 
@@ -497,9 +1451,512 @@ impl Site {
                 if let Some(dir_attachments_name) = dir_attachments.file_name() {
                     if stripped_path.starts_with(dir_attachments_name) {
                         self.cp_data();
+                        reload = Some(Reload::Page);
                     }
                 }
             }
         }
+
+        reload
+    }
+}
+
+/// the `path` argument of get_section and get_page, as the page's path in the vault.
+fn lookup_path(function: &str, args: &HashMap<String, tera::Value>) -> tera::Result<String> {
+    match args.get("path").and_then(tera::Value::as_str) {
+        Some(path) => Ok(templates::content_path(path)),
+        None => Err(tera::Error::msg(format!("{} needs a path: {}(path=\"notes/rust\")", function, function))),
+    }
+}
+
+/// the pages an incremental rebuild renders again, besides every page when the tags
+/// or sitemap change.
+#[derive(Default)]
+struct ToRender {
+    /// the files parsed again.
+    files: HashSet<PathBuf>,
+    /// the urls of the pages whose backlinks change.
+    urls: HashSet<String>,
+    /// the folders whose section page lists a page that changed.
+    sections: HashSet<PathBuf>,
+}
+
+/// the path of a file in the vault, as it is in the vault index and dependencies.
+fn vault_path(md_file: &MdFile) -> String {
+    format!("{}.md", md_file.vault_path().display())
+}
+
+/// whether the change to a file shows in the tags or sitemap, which every page gets.
+fn globals_changed(old: Option<&MdFile>, new: Option<&MdFile>) -> bool {
+    match (old, new) {
+        (Some(old), Some(new)) => {
+            old.tags != new.tags
+                || old.frontmatter.in_sitemap != new.frontmatter.in_sitemap
+                || Link::new_sitemap_link(old) != Link::new_sitemap_link(new)
+                || Link::new_tag_link_from_md_file(old) != Link::new_tag_link_from_md_file(new)
+        }
+        (None, None) => false,
+        _ => true,
+    }
+}
+
+/// the pages to parse again after a file changed: the ones embedding it and, if it was
+/// added or removed, the ones with links that may now point somewhere else.
+fn pages_to_parse(
+    markdown_files: &HashMap<PathBuf, Vec<MdFile>>,
+    path: &Path,
+    old: Option<&MdFile>,
+    new: Option<&MdFile>,
+) -> HashSet<PathBuf> {
+    let changed: Vec<String> = old.iter().chain(new.iter()).map(|f| vault_path(f)).collect();
+    let added_or_removed_stem =
+        (old.is_some() != new.is_some()).then(|| crate::dependencies::stem(&path.display().to_string()));
+
+    markdown_files
+        .values()
+        .flatten()
+        .filter(|f| {
+            changed.iter().any(|vault_path| f.dependencies.embeds.contains(vault_path))
+                || added_or_removed_stem
+                    .as_ref()
+                    .is_some_and(|stem| f.dependencies.link_stems.contains(stem))
+        })
+        .map(|f| f.path.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a vault in the temp dir with the default templates, `config` added to the default
+    /// config.yaml and the given notes (path in the vault, contents).
+    fn vault(name: &str, config: &str, notes: &[(&str, &str)]) -> PathBuf {
+        let dir = env::temp_dir().join(format!("esker-{}-{}", name, std::process::id()));
+        if dir.is_dir() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        new_site::init(Some(dir.clone()));
+        let config_path = dir.join("_esker/config.yaml");
+        let default_config = fs::read_to_string(&config_path).unwrap();
+        fs::write(&config_path, format!("{}\n{}", default_config, config)).unwrap();
+
+        for (path, contents) in notes {
+            write_note(&dir, path, contents);
+        }
+        dir
+    }
+
+    fn write_note(dir: &Path, path: &str, contents: &str) {
+        let path = dir.join(path);
+        create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn site(dir: &Path) -> Site {
+        let cmd = Commands::Build { strict: false, diagnostics_format: DiagnosticsFormat::Json };
+        let cli = Cli { dir: Some(dir.to_path_buf()), verbose: false, command: Some(cmd.clone()) };
+        Site::new(cmd, cli)
+    }
+
+    fn output(site: &Site, path: &str) -> String {
+        fs::read_to_string(site.dir_esker_site.join(path)).unwrap()
+    }
+
+    #[test]
+    fn test_escaped_wikilink() {
+        let dir = vault(
+            "escaped-wikilink",
+            "",
+            &[
+                ("note.md", "---\ntitle: Note\n---\n\\[[not a link]] but [[other]] is.\n"),
+                ("other.md", "---\ntitle: Other\n---\nhi\n"),
+            ],
+        );
+        let mut site = site(&dir);
+        site.build();
+
+        let html = output(&site, "note.html");
+        assert!(html.contains("[[not a link]] but <a href=\"http://localhost:8080/other.html\">other</a> is."));
+    }
+
+    #[test]
+    fn test_embed_is_a_block() {
+        let dir = vault(
+            "embed-block",
+            "",
+            &[
+                ("note.md", "---\ntitle: Note\n---\n# Intro\n\n![[other]]\n\nbefore ![[other]] after\n"),
+                ("other.md", "---\ntitle: Other\n---\n# Intro\n\nhi\n"),
+            ],
+        );
+        let mut site = site(&dir);
+        site.build();
+
+        let html = output(&site, "note.html");
+        let embed = r#"<div class="embed" data-embed-src="http://localhost:8080/other.html"><h1>Intro</h1>"#;
+        assert!(html.contains(&format!(r#"<h1 id="intro">Intro</h1>{}"#, embed)));
+        assert!(html.contains(&format!("<p>before </p>\n{}", embed)));
+        assert!(html.contains("</div>\n<p> after</p>"));
+        assert!(!html.contains("<p><div"));
+        assert_eq!(html.matches(r#"id="intro""#).count(), 1);
+    }
+
+    #[test]
+    fn test_recursive_embed_is_reported_once() {
+        use crate::parser::links::{ParseWikilinks, WikilinkProblem};
+
+        let dir = vault(
+            "recursive-embed",
+            "",
+            &[
+                ("a.md", "---\ntitle: A\n---\nhi ![[b]] there\n\n![[b]]\n"),
+                ("b.md", "---\ntitle: B\n---\none\n\n![[a]]\n"),
+            ],
+        );
+        let mut site = site(&dir);
+        site.build();
+
+        // a embeds b twice, so the cycle shows up twice while b's embed of a is rendered.
+        let b = site.markdown_files.values().flatten().find(|f| f.frontmatter.title == "B").unwrap();
+        let parser = pulldown_cmark::Parser::new(&b.raw).into_offset_iter();
+        let mut wikilinks = ParseWikilinks::new(parser, &b.raw, &site, b);
+        wikilinks.by_ref().for_each(drop);
+        let chain = vec!["b.md".to_string(), "a.md".to_string(), "b.md".to_string()];
+        let offset = b.raw.find("![[a]]").unwrap();
+        assert_eq!(wikilinks.finish().problems, vec![WikilinkProblem::RecursiveEmbed { offset, chain }]);
+    }
+
+    #[test]
+    fn test_check_internal_links() {
+        let dir = vault(
+            "internal-links",
+            "",
+            &[
+                (
+                    "note.md",
+                    "---\ntitle: Note\n---\n[good](other.md) and [[other]]\n\n[pdf](attachments/doc.pdf) [pdf](doc.pdf)\n",
+                ),
+                ("other.md", "---\ntitle: Other\n---\nhi\n"),
+                ("attachments/doc.pdf", "%PDF"),
+            ],
+        );
+        let mut site = site(&dir);
+        site.build();
+
+        let html = output(&site, "note.html");
+        assert_eq!(html.matches(r#"<a href="http://localhost:8080/attachments/doc.pdf""#).count(), 2);
+        assert!(site.dir_esker_site.join("attachments/doc.pdf").is_file());
+        assert!(!site.errors.has_broken_links());
+
+        write_note(&dir, "note.md", "---\ntitle: Note\n---\n[broken](missing.md)\n");
+        site.build();
+        assert!(site.errors.has_broken_links());
+    }
+
+    fn diagnostics(site: &Site) -> Vec<(Kind, String)> {
+        let mut diagnostics: Vec<(Kind, String)> =
+            site.errors.diagnostics().iter().map(|d| (d.kind, d.location())).collect();
+        diagnostics.sort();
+        diagnostics
+    }
+
+    /// every file in _site with its contents, and the diagnostics in the order they were added.
+    fn build_snapshot(dir: &Path, threads: usize) -> (Vec<(PathBuf, Vec<u8>)>, Vec<crate::errors::Diagnostic>) {
+        let mut site = site(dir);
+        let _ = fs::remove_dir_all(&site.dir_esker_site);
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        pool.install(|| site.build());
+
+        let mut files: Vec<(PathBuf, Vec<u8>)> = util::load_files(&site.dir_esker_site, "**/*")
+            .into_iter()
+            .filter(|path| path.is_file())
+            .map(|path| (path.clone(), fs::read(path).unwrap()))
+            .collect();
+        files.sort();
+        (files, site.errors.diagnostics().to_vec())
+    }
+
+    #[test]
+    fn test_parallel_build_is_deterministic() {
+        let mut notes: Vec<(String, String)> = (0..40)
+            .map(|i| {
+                let contents = format!(
+                    "---\ntitle: Note {i}\ntags: [tag-{}, all]\n---\n# Note {i}\n\n[[note-{}]] and [[missing-{i}]]\n",
+                    i % 5,
+                    (i + 1) % 40
+                );
+                (format!("posts/note-{}.md", i), contents)
+            })
+            .collect();
+        notes.push(("posts/_index.md".to_string(), "---\ntitle: Posts\n---\n".to_string()));
+        notes.push(("bad-date.md".to_string(), "---\ntitle: Bad\ndate_created: someday\n---\n".to_string()));
+        let notes: Vec<(&str, &str)> = notes.iter().map(|(path, contents)| (path.as_str(), contents.as_str())).collect();
+        let dir = vault("deterministic", "", &notes);
+
+        let (serial_files, serial_diagnostics) = build_snapshot(&dir, 1);
+        let (parallel_files, parallel_diagnostics) = build_snapshot(&dir, 8);
+        assert!(serial_files.len() > 40);
+        assert!(serial_diagnostics.len() > 40);
+        assert!(serial_files == parallel_files);
+        assert_eq!(serial_diagnostics, parallel_diagnostics);
+    }
+
+    #[test]
+    fn test_sitemap_lists_index_pages() {
+        let dir = vault(
+            "sitemap-index-pages",
+            "auto_index: true",
+            &[
+                ("notes/a.md", "---\ntitle: A\ndate_updated: 2023-01-12\n---\nhi\n"),
+                ("notes/rust/b.md", "---\ntitle: B\ndate_updated: 2023-03-04\n---\nhi\n"),
+                ("posts/_index.md", "---\ntitle: Posts\n---\n"),
+                ("posts/c.md", "---\ntitle: C\n---\nhi\n"),
+            ],
+        );
+        let mut site = site(&dir);
+        site.build();
+
+        let sitemap = output(&site, "sitemap.xml");
+        let entry = |url: &str, date: &str| format!("<loc>{}</loc>\n    <lastmod>{}</lastmod>", url, date);
+        assert!(sitemap.contains(&entry("http://localhost:8080/notes/index.html", "2023-03-04")));
+        assert!(sitemap.contains(&entry("http://localhost:8080/notes/rust/index.html", "2023-03-04")));
+        assert!(sitemap.contains(&entry("http://localhost:8080/notes/a.html", "2023-01-12")));
+        // posts has its own section page, listed like any other page.
+        assert_eq!(sitemap.matches("<loc>http://localhost:8080/posts/index.html</loc>").count(), 1);
+    }
+
+    #[test]
+    fn test_rebuild_changed_markdown_checks_links_again() {
+        let dir = vault(
+            "incremental-links",
+            "",
+            &[
+                ("a.md", "---\ntitle: A\n---\n[broken](nowhere.md)\n"),
+                ("b.md", "---\ntitle: B\n---\n[[a]]\n"),
+            ],
+        );
+        let mut site = site(&dir);
+        site.build();
+        assert!(site.errors.has_broken_links());
+
+        // the broken link in a.md is still there when another file changes...
+        write_note(&dir, "b.md", "---\ntitle: B\n---\n[[a]], edited\n");
+        site.rebuild_changed_markdown(&dir.join("b.md"));
+        assert!(site.errors.has_broken_links());
+        assert!(output(&site, "b.html").contains("edited"));
+
+        // ...and gone once it is fixed.
+        write_note(&dir, "a.md", "---\ntitle: A\n---\n[fixed](b.md)\n");
+        site.rebuild_changed_markdown(&dir.join("a.md"));
+        assert!(!site.errors.has_broken_links());
+    }
+
+    #[test]
+    fn test_rebuild_changed_markdown_removes_unused_tag_pages() {
+        let dir = vault(
+            "unused-tag-pages",
+            "feeds:\n  tags: true",
+            &[
+                ("a.md", "---\ntitle: A\ntags: [gone, kept]\n---\nhi\n"),
+                ("b.md", "---\ntitle: B\ntags: [kept]\n---\nhi\n"),
+            ],
+        );
+        let mut site = site(&dir);
+        site.build();
+        let tags = site.dir_esker_site.join("tags");
+        for path in ["gone.html", "gone/atom.xml", "gone/feed.json", "kept.html", "kept/atom.xml"] {
+            assert!(tags.join(path).is_file(), "{} wasn't written", path);
+        }
+
+        write_note(&dir, "a.md", "---\ntitle: A\ntags: [kept]\n---\nhi\n");
+        site.rebuild_changed_markdown(&dir.join("a.md"));
+        assert!(!tags.join("gone.html").exists());
+        assert!(!tags.join("gone").exists());
+        assert!(tags.join("kept.html").is_file());
+        assert!(tags.join("kept/atom.xml").is_file());
+        assert!(tags.join("kept/feed.json").is_file());
+        assert_eq!(diagnostics(&site), vec![]);
+    }
+
+    #[test]
+    fn test_url_collision_is_skipped() {
+        let dir = vault(
+            "url-collision",
+            "",
+            &[
+                ("a.md", "---\ntitle: A\npermalink: /b.html\n---\nfrom a\n"),
+                ("b.md", "---\ntitle: B\n---\nfrom b\n"),
+            ],
+        );
+        let mut site = site(&dir);
+        site.build();
+
+        assert!(output(&site, "b.html").contains("from a"));
+        let b = util::path_to_string(&dir.join("b.md"));
+        assert_eq!(diagnostics(&site), vec![(Kind::UrlCollision, b)]);
+    }
+
+    #[test]
+    fn test_url_collision_is_disambiguated() {
+        let dir = vault(
+            "url-disambiguated",
+            "disambiguate_urls: true",
+            &[
+                ("a.md", "---\ntitle: A\npermalink: /b.html\n---\nfrom a\n"),
+                ("b.md", "---\ntitle: B\n---\nfrom b\n"),
+            ],
+        );
+        let mut site = site(&dir);
+        site.build();
+
+        assert!(output(&site, "b.html").contains("from a"));
+        assert!(output(&site, "b-2.html").contains("from b"));
+        let b = util::path_to_string(&dir.join("b.md"));
+        assert_eq!(diagnostics(&site), vec![(Kind::DisambiguatedUrl, b)]);
+    }
+
+    #[test]
+    fn test_url_collision_is_disambiguated_with_clean_urls() {
+        let dir = vault(
+            "url-disambiguated-clean",
+            "disambiguate_urls: true\nclean_urls: true",
+            &[
+                ("a.md", "---\ntitle: A\nslug: b\n---\nfrom a\n"),
+                ("b.md", "---\ntitle: B\n---\nfrom b\n"),
+            ],
+        );
+        let mut site = site(&dir);
+        site.build();
+
+        assert!(output(&site, "b/index.html").contains("from a"));
+        assert!(output(&site, "b-2/index.html").contains("from b"));
+        assert!(!site.dir_esker_site.join("b-2.html").exists());
+        let b = util::path_to_string(&dir.join("b.md"));
+        assert_eq!(diagnostics(&site), vec![(Kind::DisambiguatedUrl, b)]);
+    }
+
+    #[test]
+    fn test_rebuild_changed_markdown_with_url_collisions() {
+        let dir = vault("incremental-url-collision", "", &[("b.md", "---\ntitle: B\n---\nfrom b\n")]);
+        let mut site = site(&dir);
+        site.build();
+
+        // a new page at b's url: b.md sorts first and keeps it.
+        write_note(&dir, "c.md", "---\ntitle: C\npermalink: /b.html\n---\nfrom c\n");
+        site.rebuild_changed_markdown(&dir.join("c.md"));
+        assert!(output(&site, "b.html").contains("from b"));
+        let c = util::path_to_string(&dir.join("c.md"));
+        assert_eq!(diagnostics(&site), vec![(Kind::UrlCollision, c)]);
+
+        // once b.md is gone, c.md gets its url.
+        fs::remove_file(dir.join("b.md")).unwrap();
+        site.rebuild_changed_markdown(&dir.join("b.md"));
+        assert!(output(&site, "b.html").contains("from c"));
+        assert!(diagnostics(&site).is_empty());
+    }
+
+    #[test]
+    fn test_rebuild_changed_markdown_keeps_other_diagnostics() {
+        let dir = vault(
+            "incremental-diagnostics",
+            "",
+            &[
+                ("a.md", "---\ntitle: A\n---\n[[nowhere]]\n\n![[missing.png]]\n"),
+                ("b.md", "---\ntitle: B\ndate_created: yesterday\n---\nhi\n"),
+                ("c.md", "---\ntitle: C\n---\n[[a]]\n"),
+            ],
+        );
+        let mut site = site(&dir);
+        site.build();
+        let a = util::path_to_string(&dir.join("a.md"));
+        let b = util::path_to_string(&dir.join("b.md"));
+        let c = util::path_to_string(&dir.join("c.md"));
+        let mut before = vec![
+            (Kind::InvalidDateCreated, format!("{}:3", b)),
+            (Kind::BrokenLink, format!("{}:4", a)),
+            (Kind::MissingAttachment, format!("{}:6", a)),
+        ];
+        before.sort();
+        assert_eq!(diagnostics(&site), before);
+
+        write_note(&dir, "c.md", "---\ntitle: C\n---\n[[a]], edited\n");
+        site.rebuild_changed_markdown(&dir.join("c.md"));
+        assert_eq!(diagnostics(&site), before);
+
+        write_note(&dir, "c.md", "---\ntitle: C\n---\n[[a]] and [[gone]]\n");
+        site.rebuild_changed_markdown(&dir.join("c.md"));
+        let mut with_c = before;
+        with_c.push((Kind::BrokenLink, format!("{}:4", c)));
+        with_c.sort();
+        assert_eq!(diagnostics(&site), with_c);
+    }
+
+    #[test]
+    fn test_rebuild_changed_markdown_when_a_tag_moves() {
+        let dir = vault(
+            "tag-moves",
+            "",
+            &[
+                ("a.md", "---\ntitle: A\ntags: [c-d]\n---\nabout #c-d\n"),
+                ("b.md", "---\ntitle: B\n---\nhi\n"),
+            ],
+        );
+        let mut site = site(&dir);
+        site.build();
+        assert!(output(&site, "a.html").contains(r#"href="http://localhost:8080/tags/c-d.html">#c-d</a>"#));
+
+        // "C D" sorts first and takes c-d.html: the link in a.md now goes to c-d's new page.
+        write_note(&dir, "b.md", "---\ntitle: B\ntags: [C D]\n---\nhi\n");
+        site.rebuild_changed_markdown(&dir.join("b.md"));
+        assert!(output(&site, "a.html").contains(r#"href="http://localhost:8080/tags/c-d-2.html">#c-d</a>"#));
+        assert!(output(&site, "tags/c-d-2.html").contains("A"));
+    }
+
+    #[test]
+    fn test_feed_urls_are_percent_encoded() {
+        let dir = vault(
+            "feed-urls",
+            "feeds:\n  sections: true",
+            &[
+                ("My Folder/_index.md", "---\ntitle: Folder\n---\n"),
+                ("My Folder/note.md", "---\ntitle: Note\n---\nhi\n"),
+            ],
+        );
+        let mut site = site(&dir);
+        site.build();
+
+        let atom = output(&site, "atom.xml");
+        assert!(atom.contains(r#"<link href="http://localhost:8080/My%20Folder/note.html" rel="alternate"/>"#));
+        assert!(atom.contains("<id>http://localhost:8080/My%20Folder/note.html</id>"));
+        let json = output(&site, "feed.json");
+        assert!(json.contains(r#""id": "http://localhost:8080/My%20Folder/note.html""#));
+        assert!(json.contains(r#""url": "http://localhost:8080/My%20Folder/note.html""#));
+        let section_atom = output(&site, "My Folder/atom.xml");
+        assert!(section_atom.contains(r#"<link href="http://localhost:8080/My%20Folder/atom.xml" rel="self"/>"#));
+    }
+
+    #[test]
+    fn test_rebuild_changed_markdown_when_a_page_moves() {
+        let dir = vault(
+            "page-moves",
+            "",
+            &[
+                ("a.md", "---\ntitle: A\naliases: [old-a]\n---\nhi\n"),
+                ("b.md", "---\ntitle: B\n---\n[[a]]\n"),
+            ],
+        );
+        let mut site = site(&dir);
+        site.build();
+        assert!(output(&site, "b.html").contains(r#"href="http://localhost:8080/a.html""#));
+        assert!(site.dir_esker_site.join("old-a.html").is_file());
+
+        // the slug moves a.md's page, and the links to it in pages that weren't changed.
+        write_note(&dir, "a.md", "---\ntitle: A\nslug: moved\n---\nhi\n");
+        site.rebuild_changed_markdown(&dir.join("a.md"));
+        assert!(output(&site, "b.html").contains(r#"href="http://localhost:8080/moved.html""#));
+        assert!(output(&site, "moved.html").contains("hi"));
+        assert!(!site.dir_esker_site.join("a.html").exists());
+        assert!(!site.dir_esker_site.join("old-a.html").exists());
     }
 }
@@ -0,0 +1,67 @@
+//! sitemap.xml, for search engines (https://www.sitemaps.org/protocol.html), and a
+//! robots.txt pointing them to it.
+
+use chrono::NaiveDateTime;
+
+/// a page listed in the sitemap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub url: String,
+    pub date_updated: NaiveDateTime,
+}
+
+/// the sitemap listing `entries`. Their urls are percent-encoded first, as a sitemap only
+/// takes valid urls ("My Folder/" is "My%20Folder/"), then escaped for xml.
+pub fn xml(entries: &[Entry]) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for entry in entries {
+        xml.push_str(&format!(
+            "  <url>\n    <loc>{}</loc>\n    <lastmod>{}</lastmod>\n  </url>\n",
+            html_escape::encode_text(&url_escape::encode_path(&entry.url)),
+            entry.date_updated.format("%Y-%m-%d")
+        ));
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+/// lets every crawler in, and tells them where the sitemap is.
+pub fn robots_txt(base_url: &str) -> String {
+    format!("User-agent: *\nAllow: /\n\nSitemap: {}/sitemap.xml\n", base_url.trim_end_matches('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xml() {
+        let date_updated = NaiveDateTime::parse_from_str("2023-01-12 09:30:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let entries = vec![
+            Entry {
+                url: "https://example.com/notes/a&b.html".to_string(),
+                date_updated,
+            },
+            Entry {
+                url: "https://example.com/My Folder/note.html".to_string(),
+                date_updated,
+            },
+        ];
+        assert_eq!(
+            xml(&entries),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n  \
+             <url>\n    <loc>https://example.com/notes/a&amp;b.html</loc>\n    \
+             <lastmod>2023-01-12</lastmod>\n  </url>\n  \
+             <url>\n    <loc>https://example.com/My%20Folder/note.html</loc>\n    \
+             <lastmod>2023-01-12</lastmod>\n  </url>\n</urlset>\n"
+        );
+        assert_eq!(
+            robots_txt("https://example.com/"),
+            "User-agent: *\nAllow: /\n\nSitemap: https://example.com/sitemap.xml\n"
+        );
+    }
+}
@@ -1,29 +1,27 @@
-/// this file is responsible for providing structs and their requisite methods
-/// that take internal data and prepare it for being inserted into a tera context.
+//! this file is responsible for providing structs and their requisite methods
+//! that take internal data and prepare it for being inserted into a tera context.
 
-use crate::{link::Link, md_file::MdFile, util, site::Site};
+use crate::{errors, link::{EskerLinkType, Link}, md_file::MdFile, util, site::Site};
+use crate::parser::tags::{with_parents, TagPages};
+use chrono::NaiveDateTime;
 use serde::Serialize;
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
 use tera::Tera;
 
-pub fn load_templates(dir_templates: &Path) -> Tera {
+pub fn load_templates(dir_templates: &Path) -> Result<Tera, String> {
     let template_path = format!("{}/**/*.html", util::path_to_string(dir_templates));
-    let mut tera = match Tera::new(&template_path) {
-        Ok(t) => t,
-        Err(e) => {
-            println!("Parsing error(s): {}", e);
-            util::exit();
-        }
-    };
+    let mut tera = Tera::new(&template_path)
+        .map_err(|e| format!("Parsing error(s): {}", errors::describe(&e)))?;
 
-    tera.add_template_file(dir_templates.join("feed.rss"), Some("feed.rss")).unwrap();
+    tera.add_template_file(dir_templates.join("feed.rss"), Some("feed.rss"))
+        .map_err(|e| format!("Parsing error(s): {}", errors::describe(&e)))?;
 
     tera.autoescape_on(vec![]);
     if tera.templates.is_empty() {
-        println!("\nError: No templates found in {:?}\n", template_path);
-        util::exit();
+        return Err(format!("No templates found in {:?}", template_path));
     }
-    tera
+    Ok(tera)
 }
 
 // get_template returns the name of a template (to later render), provided it's
@@ -52,18 +50,18 @@ impl Config{
     pub fn new(site: &Site) -> Self {
         Self {
             title: site.config.title.clone(),
-            description: site.config.description.clone().unwrap_or_else(|| "".to_string()),
+            description: site.config.description.clone().unwrap_or_default(),
             url: site.config.url.clone()
         }
     }
 }
 
-#[derive(Serialize, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Serialize, Debug, Eq, PartialEq)]
 pub struct Page<'a> {
     content: &'a String,
     title: &'a String,
     backlinks: &'a Vec<Link>,
-    url: &'a String,
+    pub url: &'a String,
     summary: &'a Option<String>,
     date_created: String,
     date_updated: String,
@@ -72,11 +70,13 @@ pub struct Page<'a> {
     tags: &'a Vec<String>,
     toc: &'a Vec<Link>,
     related_files: &'a Vec<Link>,
-    is_section: bool
+    is_section: bool,
+    /// custom frontmatter fields
+    extra: &'a BTreeMap<String, serde_yaml::Value>,
 }
 
 impl Page<'_> {
-    pub fn new(md_file: &MdFile) -> Page {
+    pub fn new(md_file: &MdFile) -> Page<'_> {
         Page {
             content: &md_file.html,
             title: &md_file.frontmatter.title,
@@ -87,10 +87,11 @@ impl Page<'_> {
             date_updated: util::naive_date_to_str(md_file.frontmatter.date_updated),
             date_created_timestamp: md_file.frontmatter.date_created_timestamp,
             date_updated_timestamp: md_file.frontmatter.date_updated_timestamp,
-            tags: &md_file.frontmatter.tags,
+            tags: &md_file.tags,
             toc: &md_file.toc,
             related_files: &md_file.related_files,
-            is_section: md_file.is_section
+            is_section: md_file.is_section,
+            extra: &md_file.frontmatter.extra,
 
         }
     }
@@ -99,13 +100,525 @@ impl Page<'_> {
 /// A trimmed down version of MDFile, to be accessed in section files (_index.md)
 
 #[derive(Serialize, Debug)]
-pub struct SectionPage<'a> {
-    pub pages: Vec<Page<'a>>,
+pub struct SectionPage<'a, T> {
+    pub pages: &'a [T],
+    pub title: &'a str,
+    pub path: &'a str,
+    pub url: Option<&'a str>,
+    pub parent: Option<&'a str>,
+    pub subsections: &'a [SectionNode],
+    pub page_count: usize,
+    pub total_page_count: usize,
+}
+
+
+impl<'a, T> SectionPage<'a, T> {
+    /// the pages (of this page, if the section is paginated) and the section's place in the tree.
+    pub fn new(pages: &'a [T], section: &'a SectionNode) -> SectionPage<'a, T> {
+        SectionPage {
+            pages,
+            title: &section.title,
+            path: &section.path,
+            url: section.url.as_deref(),
+            parent: section.parent.as_deref(),
+            subsections: &section.subsections,
+            page_count: section.page_count,
+            total_page_count: section.total_page_count,
+        }
+    }
+}
+
+/// Stands in for the _index.md of a folder that doesn't have one, on its generated index page.
+#[derive(Serialize, Debug)]
+pub struct IndexPage<'a> {
+    title: &'a str,
+    url: &'a str,
+    content: &'static str,
+    summary: Option<String>,
+    tags: Vec<String>,
+    toc: Vec<Link>,
+    backlinks: Vec<Link>,
+    related_files: Vec<Link>,
+    is_section: bool,
+    extra: BTreeMap<String, serde_yaml::Value>,
+}
+
+impl IndexPage<'_> {
+    pub fn new(section: &SectionNode) -> IndexPage<'_> {
+        IndexPage {
+            title: &section.title,
+            url: section.url.as_deref().unwrap_or_default(),
+            content: "",
+            summary: None,
+            tags: Vec::new(),
+            toc: Vec::new(),
+            backlinks: Vec::new(),
+            related_files: Vec::new(),
+            is_section: true,
+            extra: BTreeMap::new(),
+        }
+    }
+}
+
+/// A page as listed in the section tree: what's needed to link to it, without its content.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct PageSummary {
+    pub title: String,
+    pub url: String,
+    /// where the file is in the vault: notes/rust/ownership.md
+    pub path: String,
+    pub summary: Option<String>,
+    pub date_created: String,
+    pub date_updated: String,
+    pub date_created_timestamp: i64,
+    pub tags: Vec<String>,
+}
+
+impl PageSummary {
+    pub fn new(md_file: &MdFile) -> Self {
+        PageSummary {
+            title: md_file.frontmatter.title.clone(),
+            url: md_file.full_url.clone(),
+            path: format!("{}.md", md_file.vault_path().display()),
+            summary: md_file.frontmatter.summary.clone(),
+            date_created: util::naive_date_to_str(md_file.frontmatter.date_created),
+            date_updated: util::naive_date_to_str(md_file.frontmatter.date_updated),
+            date_created_timestamp: md_file.frontmatter.date_created_timestamp,
+            tags: md_file.tags.clone(),
+        }
+    }
+}
+
+/// A folder of the vault, and the folders in it. Folders with an _index.md are rendered as
+/// a section page, the others are only in the tree (to list their pages, or subsections).
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct SectionNode {
+    /// the _index.md's title, or the folder's name (or the site's, for the top).
+    pub title: String,
+    /// the folder in the vault: "notes/rust" ("" for the top).
+    pub path: String,
+    /// the section page, if the folder has an _index.md.
+    pub url: Option<String>,
+    /// the path of the folder it's in; None for the top.
+    pub parent: Option<String>,
+    /// the pages in the folder, newest first.
+    pub pages: Vec<PageSummary>,
+    pub subsections: Vec<SectionNode>,
+    /// how many pages are in the folder.
+    pub page_count: usize,
+    /// how many pages are in the folder, and every folder in it.
+    pub total_page_count: usize,
+}
+
+impl SectionNode {
+    /// every folder with published pages (and the ones they're in) as a tree.
+    pub fn tree(markdown_files: &HashMap<PathBuf, Vec<MdFile>>, site_title: &str) -> SectionNode {
+        let mut dirs: BTreeSet<&Path> = BTreeSet::new();
+        for dir in markdown_files.keys() {
+            dirs.extend(dir.ancestors());
+        }
+        Self::new(Path::new(""), &dirs, markdown_files, site_title)
+    }
+
+    fn new(
+        dir: &Path,
+        dirs: &BTreeSet<&Path>,
+        markdown_files: &HashMap<PathBuf, Vec<MdFile>>,
+        site_title: &str,
+    ) -> SectionNode {
+        let files = markdown_files.get(dir).map(Vec::as_slice).unwrap_or_default();
+        let index = files.iter().find(|f| f.is_section);
+        let mut pages: Vec<&MdFile> = files.iter().filter(|f| !f.is_section).collect();
+        pages.sort_by(|a, b| MdFile::newest_first(a, b));
+
+        let subsections: Vec<SectionNode> = dirs
+            .iter()
+            .filter(|subdir| subdir.parent() == Some(dir))
+            .map(|subdir| Self::new(subdir, dirs, markdown_files, site_title))
+            .collect();
+
+        let title = match (index, dir.file_name()) {
+            (Some(index), _) => index.frontmatter.title.clone(),
+            (None, Some(name)) => name.to_string_lossy().to_string(),
+            (None, None) => site_title.to_string(),
+        };
+        SectionNode {
+            title,
+            path: util::path_to_string(dir),
+            url: index.map(|index| index.full_url.clone()),
+            parent: dir.parent().map(util::path_to_string),
+            page_count: pages.len(),
+            total_page_count: pages.len() + subsections.iter().map(|s| s.total_page_count).sum::<usize>(),
+            pages: pages.into_iter().map(PageSummary::new).collect(),
+            subsections,
+        }
+    }
+
+    /// the section of a folder: "notes/rust".
+    pub fn find(&self, path: &str) -> Option<&SectionNode> {
+        if self.path == path {
+            return Some(self);
+        }
+        self.subsections.iter().find_map(|section| section.find(path))
+    }
+
+    /// gives the sections (this one and the ones under it) with pages in them but no
+    /// _index.md the url of their generated index page; returns their paths.
+    pub fn set_index_urls(&mut self, url: &impl Fn(&str) -> String) -> Vec<String> {
+        let mut paths = Vec::new();
+        if self.url.is_none() && self.total_page_count > 0 {
+            self.url = Some(url(&self.path));
+            paths.push(self.path.clone());
+        }
+        for subsection in &mut self.subsections {
+            paths.extend(subsection.set_index_urls(url));
+        }
+        paths
+    }
+
+    /// whether the tree lists the same sections and pages as `other`, leaving out when pages
+    /// were last updated: without a date_updated in the frontmatter, that's when the file was
+    /// last saved, so it changes with every edit.
+    /// `esker watch` only renders every page again when this is false, so the pages it
+    /// doesn't render keep the old date_updated of the page that changed in their
+    /// `section_tree` and `get_section`, until they are rendered again.
+    pub fn same_listing(&self, other: &SectionNode) -> bool {
+        self.without_dates_updated() == other.without_dates_updated()
+    }
+
+    fn without_dates_updated(&self) -> SectionNode {
+        SectionNode {
+            pages: self
+                .pages
+                .iter()
+                .map(|page| PageSummary { date_updated: String::new(), ..page.clone() })
+                .collect(),
+            subsections: self.subsections.iter().map(Self::without_dates_updated).collect(),
+            ..self.clone()
+        }
+    }
+
+    /// the section and every section under it.
+    pub fn descendants(&self) -> Vec<&SectionNode> {
+        let mut sections = vec![self];
+        for subsection in &self.subsections {
+            sections.extend(subsection.descendants());
+        }
+        sections
+    }
+}
+
+/// a path as given to get_page or get_section, as the file's path in the vault without
+/// ".md": "/notes/rust/_index.md" -> "notes/rust", "notes/ownership.md" -> "notes/ownership".
+pub fn content_path(path: &str) -> String {
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".md").unwrap_or(path);
+    let path = match path.strip_suffix("/_index") {
+        Some(dir) => dir,
+        None if path == "_index" => "",
+        None => path,
+    };
+    path.trim_end_matches('/').to_string()
+}
+
+/// One page of a section or tag page's list of pages, with links to the others. Without
+/// `paginate_by`, there's one page with everything on it.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct Paginator<'a, T> {
+    /// the pages on this page.
+    pub pages: &'a [T],
+    /// which page this is, starting at 1.
+    pub current: usize,
+    /// how many pages there are.
+    pub total: usize,
+    pub paginate_by: Option<usize>,
+    /// how many pages there are on all of the pages.
+    pub total_pages: usize,
+    pub url: String,
+    pub first_url: String,
+    pub last_url: String,
+    pub prev_url: Option<String>,
+    pub next_url: Option<String>,
+}
+
+impl<'a, T> Paginator<'a, T> {
+    /// splits `pages` up, `paginate_by` to a page. `url` is the url of the nth page.
+    pub fn paginate(pages: &'a [T], paginate_by: Option<usize>, url: impl Fn(usize) -> String) -> Vec<Self> {
+        let paginate_by = paginate_by.filter(|paginate_by| *paginate_by > 0);
+        let chunks: Vec<&[T]> = match paginate_by {
+            Some(paginate_by) if !pages.is_empty() => pages.chunks(paginate_by).collect(),
+            _ => vec![pages],
+        };
+        let total = chunks.len();
+        chunks
+            .into_iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                let current = i + 1;
+                Paginator {
+                    pages: chunk,
+                    current,
+                    total,
+                    paginate_by,
+                    total_pages: pages.len(),
+                    url: url(current),
+                    first_url: url(1),
+                    last_url: url(total),
+                    prev_url: (current > 1).then(|| url(current - 1)),
+                    next_url: (current < total).then(|| url(current + 1)),
+                }
+            })
+            .collect()
+    }
+}
+
+/// where the nth page of a paginated list goes, in the directory of the first one:
+/// posts/index.html -> posts/page/2/index.html
+pub fn pagination_path(dir: &Path, current: usize) -> PathBuf {
+    dir.join("page").join(current.to_string()).join("index.html")
+}
+
+/// What themes need to know about a tag, to list or link to it (a tag cloud).
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TagInfo {
+    /// the last part of the tag: "sleep"
+    pub name: String,
+    /// the whole tag: "area/health/sleep"
+    pub tag: String,
+    /// the tag's page, if the site has tag pages (see `tags_url`).
+    pub url: Option<String>,
+    /// how many pages are tagged with exactly this tag.
+    pub count: usize,
+    /// the creation dates of the oldest and newest page with the tag.
+    pub first_used: Option<String>,
+    pub last_used: Option<String>,
+}
+
+impl TagInfo {
+    pub fn new(tag: &str, pages: &[Link], tag_pages: &TagPages) -> Self {
+        let dates: Vec<NaiveDateTime> = pages
+            .iter()
+            .filter_map(|link| match &link.link_type {
+                EskerLinkType::TaggedItem { date_created } => Some(*date_created),
+                _ => None,
+            })
+            .collect();
+        TagInfo {
+            name: tag.rsplit('/').next().unwrap_or_default().to_string(),
+            tag: tag.to_string(),
+            url: tag_pages.url(tag),
+            count: pages.len(),
+            first_used: dates.iter().min().map(|date| util::naive_date_to_str(*date)),
+            last_used: dates.iter().max().map(|date| util::naive_date_to_str(*date)),
+        }
+    }
+
+    /// every tag used on the site, sorted (ignoring case).
+    pub fn list(tags: &HashMap<String, Vec<Link>>, tag_pages: &TagPages) -> Vec<TagInfo> {
+        let mut list: Vec<TagInfo> = tags
+            .iter()
+            .map(|(tag, pages)| TagInfo::new(tag, pages, tag_pages))
+            .collect();
+        list.sort_by(|a, b| (a.tag.to_lowercase(), &a.tag).cmp(&(b.tag.to_lowercase(), &b.tag)));
+        list
+    }
+}
+
+/// A tag in the tag tree: "area/health/sleep" is the "sleep" child of "health", itself a
+/// child of "area". Every level gets a page, even when nothing is tagged with it directly.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TagNode {
+    #[serde(flatten)]
+    pub info: TagInfo,
+    /// the pages tagged with exactly this tag.
+    pub pages: Vec<Link>,
+    pub children: Vec<TagNode>,
+}
+
+impl TagNode {
+    /// the tags of a site, as a tree (sorted by name at every level).
+    pub fn tree(tags: &HashMap<String, Vec<Link>>, tag_pages: &TagPages) -> Vec<TagNode> {
+        let all_tags = with_parents(tags.keys());
+        Self::children_of(None, &all_tags, tags, tag_pages)
+    }
+
+    /// a node above the whole tree, for the page listing every tag.
+    pub fn root(tree: Vec<TagNode>, tag_pages: &TagPages) -> TagNode {
+        let mut info = TagInfo::new("", &[], tag_pages);
+        info.url = tag_pages.index_url();
+        TagNode {
+            info,
+            pages: Vec::new(),
+            children: tree,
+        }
+    }
+
+    /// the node and every node under it, a level at a time.
+    pub fn descendants(&self) -> Vec<&TagNode> {
+        let mut nodes = vec![self];
+        let mut i = 0;
+        while i < nodes.len() {
+            nodes.extend(nodes[i].children.iter());
+            i += 1;
+        }
+        nodes
+    }
+
+    /// finds a tag anywhere in the tree.
+    pub fn find<'a>(tree: &'a [TagNode], tag: &str) -> Option<&'a TagNode> {
+        tree.iter().find_map(|node| {
+            if node.info.tag == tag {
+                Some(node)
+            } else if tag.starts_with(&format!("{}/", node.info.tag)) {
+                Self::find(&node.children, tag)
+            } else {
+                None
+            }
+        })
+    }
+
+    fn children_of(
+        parent: Option<&str>,
+        all_tags: &BTreeSet<String>,
+        tags: &HashMap<String, Vec<Link>>,
+        tag_pages: &TagPages,
+    ) -> Vec<TagNode> {
+        all_tags
+            .iter()
+            .filter(|tag| tag.rsplit_once('/').map(|(parent, _)| parent) == parent)
+            .map(|tag| {
+                let pages = tags.get(tag).cloned().unwrap_or_default();
+                TagNode {
+                    info: TagInfo::new(tag, &pages, tag_pages),
+                    pages,
+                    children: Self::children_of(Some(tag), all_tags, tags, tag_pages),
+                }
+            })
+            .collect()
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paginate() {
+        let pages: Vec<usize> = (1..=5).collect();
+        let url = |n: usize| format!("/posts/page/{}/index.html", n);
+        let paginators = Paginator::paginate(&pages, Some(2), url);
+        assert_eq!(paginators.len(), 3);
+        assert_eq!(paginators[0].pages, &[1, 2]);
+        assert_eq!(paginators[0].prev_url, None);
+        assert_eq!(paginators[0].next_url.as_deref(), Some("/posts/page/2/index.html"));
+        assert_eq!(paginators[2].pages, &[5]);
+        assert_eq!(paginators[2].current, 3);
+        assert_eq!(paginators[2].total_pages, 5);
+        assert_eq!(paginators[2].next_url, None);
+
+        assert_eq!(Paginator::paginate(&pages, None, url)[0].pages.len(), 5);
+        assert_eq!(Paginator::paginate(&pages, Some(0), url).len(), 1);
+        assert_eq!(Paginator::paginate(&[] as &[usize], Some(2), url).len(), 1);
+    }
+
+    #[test]
+    fn test_tag_tree() {
+        let page = |url: &str, date_created: &str| {
+            let mut link = Link::empty();
+            link.url = url.to_string();
+            let date_created = NaiveDateTime::parse_from_str(date_created, "%Y-%m-%d %H:%M:%S%.f").unwrap();
+            link.link_type = EskerLinkType::TaggedItem { date_created };
+            link
+        };
+        let mut tags = HashMap::new();
+        tags.insert("area/health/sleep".to_string(), vec![page("/a.html", "2022-10-12 05:30:00")]);
+        tags.insert(
+            "area".to_string(),
+            vec![page("/b.html", "2023-01-02 10:00:00"), page("/c.html", "2021-06-01 08:15:00")],
+        );
+        tags.insert("books".to_string(), vec![page("/c.html", "2021-06-01 08:15:00")]);
+        // file times (the default date_created) have fractions of a second.
+        tags.insert("mtime".to_string(), vec![page("/d.html", "2024-03-05 17:45:12.123456789")]);
+
+        let tag_pages = TagPages::new(Some("/tags".to_string()), false, tags.keys());
+        let tree = TagNode::tree(&tags, &tag_pages);
+        let names: Vec<&str> = tree.iter().map(|node| node.info.tag.as_str()).collect();
+        assert_eq!(names, vec!["area", "books", "mtime"]);
+        assert_eq!(tree[0].pages.len(), 2);
+        assert_eq!(tree[0].info.count, 2);
+        assert_eq!(tree[0].info.first_used.as_deref(), Some("2021-06-01 08:15"));
+        assert_eq!(tree[0].info.last_used.as_deref(), Some("2023-01-02 10:00"));
+
+        let health = TagNode::find(&tree, "area/health").unwrap();
+        assert_eq!(health.info.name, "health");
+        assert_eq!(health.info.url.as_deref(), Some("/tags/area/health.html"));
+        assert_eq!(health.info.first_used, None);
+        assert!(health.pages.is_empty());
+        assert_eq!(health.children[0].info.tag, "area/health/sleep");
+        assert_eq!(health.children[0].pages[0].url, "/a.html");
+        assert!(TagNode::find(&tree, "area/nothing").is_none());
+
+        let mtime = TagNode::find(&tree, "mtime").unwrap();
+        assert_eq!(mtime.info.first_used.as_deref(), Some("2024-03-05 17:45"));
+        assert_eq!(mtime.info.last_used.as_deref(), Some("2024-03-05 17:45"));
+    }
+
+    #[test]
+    fn test_same_listing() {
+        let page = |title: &str, date_updated: &str| PageSummary {
+            title: title.to_string(),
+            url: format!("/{}.html", title),
+            path: format!("{}.md", title),
+            summary: None,
+            date_created: "2023-01-02 10:00".to_string(),
+            date_updated: date_updated.to_string(),
+            date_created_timestamp: 0,
+            tags: vec![],
+        };
+        let section = |pages| SectionNode {
+            subsections: vec![SectionNode { path: "notes".to_string(), pages, ..SectionNode::default() }],
+            ..SectionNode::default()
+        };
+
+        let tree = section(vec![page("a", "2023-01-02 10:00")]);
+        assert!(tree.same_listing(&section(vec![page("a", "2023-03-04 11:00")])));
+        assert!(!tree.same_listing(&section(vec![page("b", "2023-01-02 10:00")])));
+        assert!(!tree.same_listing(&section(vec![])));
+    }
+
+    #[test]
+    fn test_set_index_urls() {
+        let section = |path: &str, url: Option<&str>, total_page_count, subsections| SectionNode {
+            path: path.to_string(),
+            url: url.map(String::from),
+            total_page_count,
+            subsections,
+            ..SectionNode::default()
+        };
+        let mut tree = section(
+            "",
+            Some("/index.html"),
+            3,
+            vec![
+                section("notes", None, 2, vec![section("notes/rust", None, 2, vec![]), section("notes/empty", None, 0, vec![])]),
+                section("posts", Some("/posts/index.html"), 1, vec![]),
+            ],
+        );
+        let paths = tree.set_index_urls(&|path| format!("/{}/index.html", path));
+        assert_eq!(paths, vec!["notes", "notes/rust"]);
+        assert_eq!(tree.find("notes/rust").unwrap().url.as_deref(), Some("/notes/rust/index.html"));
+        assert_eq!(tree.find("notes/empty").unwrap().url, None);
+        assert_eq!(tree.find("posts").unwrap().url.as_deref(), Some("/posts/index.html"));
+    }
 
-impl SectionPage<'_> {
-    pub fn new(pages: Vec<Page>) -> SectionPage {
-        SectionPage { pages }
+    #[test]
+    fn test_content_path() {
+        assert_eq!(content_path("/notes/rust/_index.md"), "notes/rust");
+        assert_eq!(content_path("notes/rust/"), "notes/rust");
+        assert_eq!(content_path("notes/ownership.md"), "notes/ownership");
+        assert_eq!(content_path("_index.md"), "");
+        assert_eq!(content_path("/"), "");
+        assert_eq!(content_path("notes/my_index.md"), "notes/my_index");
+        assert_eq!(content_path("foo_index"), "foo_index");
     }
 }
@@ -39,8 +39,22 @@ pub fn path_to_string(p: &Path) -> String {
     p.display().to_string()
 }
 
+/// the url path of a page written to `html_path` in _site. With clean urls, pages are
+/// written to the index.html of a directory and linked to as the directory:
+/// "notes/idea/index.html" -> "notes/idea/".
+pub fn page_url_path(html_path: &str, clean_urls: bool) -> String {
+    if !clean_urls {
+        return html_path.to_string();
+    }
+    match html_path.strip_suffix("/index.html") {
+        Some(dir) => format!("{}/", dir),
+        None if html_path == "index.html" => String::new(),
+        None => html_path.to_string(),
+    }
+}
+
 pub fn naive_date_to_str(ndt: NaiveDateTime) -> String {
-    return ndt.format("%Y-%m-%d %H:%M").to_string();
+    ndt.format("%Y-%m-%d %H:%M").to_string()
 }
 
 // when provided the current directory esker is run in (ex: /Users/my_site/test-site)
@@ -53,6 +67,21 @@ pub fn strip_pwd(pwd: &Path, p: &Path) -> PathBuf {
     p.strip_prefix(pwd).unwrap().parent().unwrap().to_path_buf()
 }
 
+/// how many characters have to be added, removed or swapped to turn `a` into `b`.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
 #[cfg(test)]
 mod tests {
     use std::{path::{Path, PathBuf}, env};
@@ -78,10 +107,28 @@ mod tests {
         assert_eq!(res, example_date_str);
     }
 
+    #[test]
+    fn test_page_url_path() {
+        assert_eq!(util::page_url_path("notes/idea/index.html", true), "notes/idea/");
+        assert_eq!(util::page_url_path("index.html", true), "");
+        assert_eq!(util::page_url_path("notes/idea/index.html", false), "notes/idea/index.html");
+        assert_eq!(util::page_url_path("notes/idea.html", true), "notes/idea.html");
+        assert_eq!(util::page_url_path("old/myindex.html", true), "old/myindex.html");
+        assert_eq!(util::page_url_path("myindex.html", true), "myindex.html");
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(util::edit_distance("title", "title"), 0);
+        assert_eq!(util::edit_distance("titel", "title"), 2);
+        assert_eq!(util::edit_distance("tag", "tags"), 1);
+        assert_eq!(util::edit_distance("", "abc"), 3);
+    }
+
     #[test]
     fn test_load_files() {
         let cwd = env::current_dir().unwrap();
         let res = load_files(&cwd, "tests/example_site/**/*.md");
-        assert!(res.len() > 0);
+        assert!(!res.is_empty());
     }
 }
@@ -1,4 +1,5 @@
 #![allow(unused_imports)]
+#![allow(clippy::bool_assert_comparison)]
 use std::{env, fs::remove_dir_all, path::PathBuf};
 
 #[test]
@@ -47,4 +48,15 @@ fn cli_tests() {
     assert_eq!(new_esker_path.join("_site/public").is_dir(), true);
     assert_eq!(new_esker_path.join("_site/tags").is_dir(), true);
     assert_eq!(new_esker_path.join("_site/feed.rss").is_file(), true);
+
+    // -- Test build --strict with broken links ------
+
+    let broken_links_esker_path = cwd.join("tests/broken_links_site/_esker");
+    if broken_links_esker_path.is_dir() {
+        remove_dir_all(broken_links_esker_path.clone()).unwrap();
+    }
+
+    trycmd::TestCases::new().case("tests/cmd/build_strict.md");
+    assert_eq!(broken_links_esker_path.join("_site/attachments/doc.pdf").is_file(), true);
+    remove_dir_all(broken_links_esker_path).unwrap();
 }